@@ -0,0 +1,58 @@
+mod span;
+pub use span::*;
+
+pub trait Inner {
+    type Target;
+    fn inner(self) -> Self::Target;
+    fn inner_ref(&self) -> &Self::Target;
+    fn inner_mut(&mut self) -> &mut Self::Target;
+}
+
+#[derive(Clone)]
+pub struct Node<T, Attr> (T, Attr);
+impl<T, Attr> Node<T, Attr> {
+    pub fn new(t: T, attr: Attr) -> Self {
+        Self (t, attr)
+    }
+    pub fn attr(&self) -> &Attr {
+        &self.1
+    }
+    pub fn map<U, F>(self, f: F) -> Node<U, Attr>
+    where F: FnOnce(T) -> U {
+        Node (f(self.0), self.1)
+    }
+}
+impl<T, Attr> Inner for Node<T, Attr> {
+    type Target = T;
+    fn inner(self) -> Self::Target {
+        self.0
+    }
+    fn inner_ref(&self) -> &Self::Target {
+        &self.0
+    }
+    fn inner_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+/// A node attached with its source span.
+pub type Spanned<T> = Node<T, Span>;
+impl<T> Spanned<T> {
+    pub fn span(&self) -> Span {
+        self.1
+    }
+}
+
+/// Printing Ast.
+mod print {
+    use super::*;
+    use std::fmt;
+
+    /// Attributes are transparent when printing.
+    impl<T, Attr> fmt::Debug for Node<T, Attr>
+    where T: fmt::Debug {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+}
@@ -0,0 +1,95 @@
+//! Source positioning. Every ast node is attached with a `Span`.
+
+use std::fmt;
+
+/// A position in the source; `line` and `col` are 1-based,
+/// `col` counted in chars.
+#[derive(Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Loc {
+    pub offset: usize,
+    pub line: usize,
+    pub col: usize,
+}
+
+/// A half-open range `[start, end)` in the source.
+#[derive(Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Span {
+    pub start: Loc,
+    pub end: Loc,
+}
+
+impl Span {
+    pub fn new(start: Loc, end: Loc) -> Self {
+        Self { start, end }
+    }
+    /// The smallest span covering both.
+    pub fn join(self, other: Span) -> Self {
+        Self {
+            start: self.start.min(other.start),
+            end: self.end.max(other.end),
+        }
+    }
+}
+
+/// A named source text, indexed by lines for locating byte offsets.
+#[derive(Clone, Debug)]
+pub struct SrcFile {
+    pub name: String,
+    pub text: String,
+    lines: Vec<usize>,
+}
+
+impl SrcFile {
+    pub fn new<N, T>(name: N, text: T) -> Self
+    where N: Into<String>, T: Into<String> {
+        let name = name.into();
+        let text = text.into();
+        let lines = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(i, _)| i + 1))
+            .collect();
+        Self { name, text, lines }
+    }
+
+    pub fn loc(&self, offset: usize) -> Loc {
+        let line = match self.lines.binary_search(&offset) {
+            Ok(i) => i,
+            Err(i) => i - 1,
+        };
+        let col = self.text[self.lines[line]..offset].chars().count();
+        Loc { offset, line: line + 1, col: col + 1 }
+    }
+
+    pub fn span(&self, start: usize, end: usize) -> Span {
+        Span::new(self.loc(start), self.loc(end))
+    }
+
+    /// The text of the 1-based `line`, without the line break.
+    pub fn line(&self, line: usize) -> &str {
+        let start = self.lines[line - 1];
+        let end = self.lines.get(line).copied().unwrap_or(self.text.len());
+        self.text[start..end].trim_end_matches(&['\n', '\r'][..])
+    }
+
+    /// The number of lines; an empty text has one line.
+    pub fn lines(&self) -> usize {
+        self.lines.len()
+    }
+}
+
+impl fmt::Debug for Loc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+impl fmt::Display for Loc {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.col)
+    }
+}
+
+impl fmt::Debug for Span {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}-{:?}", self.start, self.end)
+    }
+}
+
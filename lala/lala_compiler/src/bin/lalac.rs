@@ -1,39 +1,59 @@
 use std::io::Read;
 
-use lala_compiler::lala;
+use lala_compiler::{
+    base::SrcFile,
+    diagnostic::{Diagnostic, Level},
+    external::parse,
+};
 
 fn main() -> anyhow::Result<()> {
+    let args: Vec<String> = std::env::args().skip(1).collect();
 
-    if std::env::args().len() > 1 {
+    if args.iter().any(|arg| arg == "--cases") {
         for (i, code) in code_base().iter().enumerate() {
             println!(">>>>>> Parsing: Case {} >>>>>>", i);
-            parse_nana(code);
+            let _ = parse_nana(&SrcFile::new(format!("case {}", i), code));
         }
         return Ok(())
     }
 
     // get the file[s] and combine them in simultaneity, and then...
 
-    let mut buf = String::new();
-    std::io::stdin().read_to_string(&mut buf)?;
+    // diagnostics go to stderr, and it exits with 1 if any file fails
+    let mut ok = true;
+    if args.is_empty() {
+        let mut buf = String::new();
+        std::io::stdin().read_to_string(&mut buf)?;
+        ok &= parse_nana(&SrcFile::new("<stdin>", buf));
+    }
+    for path in args {
+        let text = std::fs::read_to_string(&path)?;
+        ok &= parse_nana(&SrcFile::new(path, text));
+    }
 
-    parse_nana(&buf);
-    
+    if !ok {
+        std::process::exit(1)
+    }
     Ok(())
 }
 
+/// Prints the diagnostics to stderr, telling if none is an error.
+fn report(src: &SrcFile, diags: &[Diagnostic]) -> bool {
+    for diag in diags.iter() {
+        eprintln!("{}", diag.render(src));
+    }
+    diags.iter().all(|d| d.level != Level::Error)
+}
+
 
-fn parse_nana(code: &String) {
-    let res = lala::LalaParser::new().parse(code);
-    match res {
-        Ok(res) => {
-            println!("{:#?}", res);
-        }
-        Err(e) => {
-            println!("Error: {}", e);
-        }
+fn parse_nana(src: &SrcFile) -> bool {
+    let (res, diags) = parse(src);
+    let ok = report(src, &diags);
+    if let (Some(res), true) = (res, ok) {
+        println!("{:#?}", res);
     }
     println!("{}", "=".repeat(80));
+    ok
 }
 
 
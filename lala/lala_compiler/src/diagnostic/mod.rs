@@ -0,0 +1,6 @@
+//! Diagnostics reported to the user, rendered as labelled source snippets.
+
+pub mod report;
+pub mod parse;
+
+pub use report::{Diagnostic, Label, Level};
@@ -0,0 +1,96 @@
+//! Converting lalrpop's `ParseError` into diagnostics.
+
+use super::Diagnostic;
+use crate::base::*;
+use crate::lexer::{LexError, LexErrorKind, Token};
+use lalrpop_util::{ErrorRecovery, ParseError};
+
+/// Reports a `ParseError` from the generated parser.
+pub fn from_parse_error(err: ParseError<Loc, Token, LexError>) -> Diagnostic {
+    match err {
+        ParseError::InvalidToken { location } => {
+            let span = Span::new(location, location);
+            Diagnostic::error("invalid token")
+                .with_primary(span, "not recognized by lala")
+        }
+        ParseError::UnrecognizedEOF { location, expected } => {
+            let span = Span::new(location, location);
+            Diagnostic::error("unexpected end of file")
+                .with_primary(span, expecting(&expected))
+        }
+        ParseError::UnrecognizedToken { token: (l, tok, r), expected } => {
+            let span = Span::new(l, r);
+            Diagnostic::error(format!("unexpected token `{}`", tok))
+                .with_primary(span, expecting(&expected))
+        }
+        ParseError::ExtraToken { token: (l, tok, r) } => {
+            let span = Span::new(l, r);
+            Diagnostic::error(format!("extra token `{}`", tok))
+                .with_primary(span, "unexpected after the end of input")
+        }
+        ParseError::User { error } => from_lex_error(error),
+    }
+}
+
+/// Reports a syntax error the parser has recovered from.
+pub fn from_error_recovery(
+    rec: ErrorRecovery<Loc, Token, LexError>
+) -> Diagnostic {
+    let ErrorRecovery { error, dropped_tokens } = rec;
+    let diag = from_parse_error(error);
+    let dropped = dropped_tokens
+        .iter()
+        .map(|&(l, _, r)| Span::new(l, r))
+        .reduce(Span::join);
+    match dropped {
+        Some(span) if Some(span) != diag.span() => {
+            diag.with_secondary(span, "skipped")
+        }
+        _ => diag,
+    }
+}
+
+pub fn from_lex_error(error: LexError) -> Diagnostic {
+    let label = match error.kind {
+        LexErrorKind::UnexpectedChar(_) => "not recognized by lala",
+        LexErrorKind::UnterminatedComment => "the comment starts here",
+        LexErrorKind::UnterminatedStr => "the string starts here",
+        LexErrorKind::InvalidEscape(_) => "not a valid escape",
+        LexErrorKind::InvalidUnicode => "expected `\\u{...}` of a code point",
+    };
+    Diagnostic::error(error.to_string()).with_primary(error.span, label)
+}
+
+/// A human-readable "expected one of" message.
+fn expecting(expected: &[String]) -> String {
+    let mut names: Vec<String> = Vec::new();
+    for name in expected.iter().map(|e| describe(e)) {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    // punctuations first
+    names.sort_by_key(|name| !name.starts_with('`'));
+    match names.as_slice() {
+        [] => "unexpected here".to_owned(),
+        [one] => format!("expected {}", one),
+        [init @ .., last] => {
+            format!("expected one of {} or {}", init.join(", "), last)
+        }
+    }
+}
+
+/// Names a terminal as printed by lalrpop.
+fn describe(terminal: &str) -> String {
+    let name = match terminal.trim_matches('"') {
+        "ident" => "an identifier",
+        "op" => "an operator",
+        "int" => "an integer",
+        "float" => "a float",
+        "str" => "a string",
+        "raw" => "a raw literal",
+        punct => return format!("`{}`", punct),
+    };
+    name.to_owned()
+}
+
@@ -0,0 +1,129 @@
+use crate::base::*;
+use std::fmt::Write;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Level {
+    Error,
+    Warning,
+}
+
+/// A message attached to a span of the source.
+#[derive(Clone, Debug)]
+pub struct Label {
+    pub span: Span,
+    pub msg: String,
+    pub primary: bool,
+}
+
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    pub level: Level,
+    pub msg: String,
+    pub labels: Vec<Label>,
+    pub notes: Vec<String>,
+}
+
+impl Diagnostic {
+    pub fn error<S: Into<String>>(msg: S) -> Self {
+        Self::new(Level::Error, msg)
+    }
+    pub fn warning<S: Into<String>>(msg: S) -> Self {
+        Self::new(Level::Warning, msg)
+    }
+    fn new<S: Into<String>>(level: Level, msg: S) -> Self {
+        Self {
+            level,
+            msg: msg.into(),
+            labels: Vec::new(),
+            notes: Vec::new(),
+        }
+    }
+
+    /// The span the diagnostic is reported at.
+    pub fn with_primary<S: Into<String>>(mut self, span: Span, msg: S) -> Self {
+        let msg = msg.into();
+        self.labels.push(Label { span, msg, primary: true });
+        self
+    }
+    /// Any other span that helps explaining the diagnostic.
+    pub fn with_secondary<S>(mut self, span: Span, msg: S) -> Self
+    where S: Into<String> {
+        let msg = msg.into();
+        self.labels.push(Label { span, msg, primary: false });
+        self
+    }
+    pub fn with_note<S: Into<String>>(mut self, note: S) -> Self {
+        self.notes.push(note.into());
+        self
+    }
+
+    /// The span of the first primary label, if any.
+    pub fn span(&self) -> Option<Span> {
+        self.labels.iter().find(|l| l.primary).map(|l| l.span)
+    }
+
+    /// Renders the diagnostic against its source, e.g.
+    ///
+    /// ```text
+    /// error: unexpected token `)`
+    ///  --> main.la:1:6
+    ///   |
+    /// 1 | a := );
+    ///   |      ^ expected an expression
+    /// ```
+    pub fn render(&self, src: &SrcFile) -> String {
+        let mut out = String::new();
+        let level = match self.level {
+            Level::Error => "error",
+            Level::Warning => "warning",
+        };
+        let _ = writeln!(out, "{}: {}", level, self.msg);
+
+        let mut labels: Vec<&Label> = self.labels.iter().collect();
+        labels.sort_by_key(|l| (l.span.start, !l.primary));
+        let gutter = labels.iter()
+            .map(|l| l.span.start.line.to_string().len())
+            .max()
+            .unwrap_or(0);
+        let pad = " ".repeat(gutter);
+
+        if let Some(loc) = self.span().map(|s| s.start) {
+            let _ = writeln!(out, "{}--> {}:{}", pad, src.name, loc);
+        } else {
+            let _ = writeln!(out, "{}--> {}", pad, src.name);
+        }
+        if !labels.is_empty() {
+            let _ = writeln!(out, "{} |", pad);
+        }
+        let mut last_line = None;
+        for label in labels {
+            let line = label.span.start.line;
+            let text = src.line(line);
+            if last_line != Some(line) {
+                let _ = writeln!(out, "{:>w$} | {}", line, text, w = gutter);
+                last_line = Some(line);
+            }
+            let _ = writeln!(
+                out, "{} | {}{} {}",
+                pad, " ".repeat(label.span.start.col - 1),
+                underline(label, text), label.msg
+            );
+        }
+        for note in self.notes.iter() {
+            let _ = writeln!(out, "{} = note: {}", pad, note);
+        }
+        out
+    }
+}
+
+/// Carets under the part of the label on its first line; at least one.
+fn underline(label: &Label, text: &str) -> String {
+    let Span { start, end } = label.span;
+    let width = if end.line == start.line {
+        end.col.saturating_sub(start.col)
+    } else {
+        (text.chars().count() + 1).saturating_sub(start.col)
+    };
+    let mark = if label.primary { "^" } else { "-" };
+    mark.repeat(width.max(1))
+}
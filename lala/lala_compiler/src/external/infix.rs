@@ -0,0 +1,102 @@
+//! Infix operators, desugared into applications of operator binders,
+//! i.e. `a + b` is `(+) a b`; thus `(+)` can be sectioned or redefined
+//! as any other binder.
+
+use super::lala_ast::*;
+use std::iter::Peekable;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Assoc {
+    Left,
+    Right,
+}
+
+/// Precedence and associativity of operators; the higher binds tighter.
+/// Operators not listed bind the tightest, to the left.
+pub const PRECEDENCE: &[(&str, u8, Assoc)] = &[
+    ("$", 0, Assoc::Right),
+    ("&&", 3, Assoc::Right),
+    ("==", 4, Assoc::Left),
+    ("!=", 4, Assoc::Left),
+    ("<", 4, Assoc::Left),
+    (">", 4, Assoc::Left),
+    ("<=", 4, Assoc::Left),
+    (">=", 4, Assoc::Left),
+    ("++", 5, Assoc::Right),
+    ("+", 6, Assoc::Left),
+    ("-", 6, Assoc::Left),
+    ("*", 7, Assoc::Left),
+    ("/", 7, Assoc::Left),
+    ("%", 7, Assoc::Left),
+    ("**", 8, Assoc::Right),
+    ("^", 8, Assoc::Right),
+];
+
+pub fn precedence(op: &str) -> (u8, Assoc) {
+    PRECEDENCE
+        .iter()
+        .find(|(o, _, _)| *o == op)
+        .map_or((9, Assoc::Left), |&(_, prec, assoc)| (prec, assoc))
+}
+
+/// Associates `first (op operand)*` by precedence climbing.
+pub fn climb(
+    first: Spanned<Expr>, rest: Vec<(Spanned<String>, Spanned<Expr>)>
+) -> Spanned<Expr> {
+    climb_from(first, &mut rest.into_iter().peekable(), 0)
+}
+
+fn climb_from<I>(
+    mut lhs: Spanned<Expr>, rest: &mut Peekable<I>, min: u8
+) -> Spanned<Expr>
+where I: Iterator<Item = (Spanned<String>, Spanned<Expr>)> {
+    while let Some((op, _)) = rest.peek() {
+        let (prec, _) = precedence(op.inner_ref());
+        if prec < min {
+            break
+        }
+        let (op, mut rhs) = rest.next().expect("peeked");
+        while let Some((next, _)) = rest.peek() {
+            let (next_prec, next_assoc) = precedence(next.inner_ref());
+            if next_prec > prec {
+                rhs = climb_from(rhs, rest, prec + 1);
+            } else if next_prec == prec && next_assoc == Assoc::Right {
+                rhs = climb_from(rhs, rest, prec);
+            } else {
+                break
+            }
+        }
+        lhs = apply(op, lhs, rhs);
+    }
+    lhs
+}
+
+/// `lhs op rhs` as `(op) lhs rhs`.
+fn apply(
+    op: Spanned<String>, lhs: Spanned<Expr>, rhs: Spanned<Expr>
+) -> Spanned<Expr> {
+    let partial = lhs.span().join(op.span());
+    let span = partial.join(rhs.span());
+    let op = op.map(|op| Binder::from(op).into());
+    let partial = Node::new(Application::from((op, lhs)).into(), partial);
+    Node::new(Application::from((partial, rhs)).into(), span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external::parse;
+
+    #[test]
+    fn climbing() {
+        let src = SrcFile::new("main.la", "a + b * c ** d ** e - f $ (+) g");
+        let (lala, _) = parse(&src);
+        let lala = lala.unwrap();
+        let expr = match lala.body.inner_ref() {
+            Expr::Block(Block::Tuple(inner)) => inner.vls[0].inner_ref(),
+            _ => unreachable!(),
+        };
+        let expected = "(($ ((- ((+ a) ((* b) ((** c) ((** d) e))))) f)) (+ g))";
+        assert_eq!(format!("{:?}", expr), expected);
+    }
+}
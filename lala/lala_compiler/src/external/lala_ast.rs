@@ -1,6 +1,9 @@
+pub use crate::base::*;
+use num_bigint::BigInt;
+
 #[derive(Clone)]
 pub struct Lala {
-    pub body: Expr,
+    pub body: Spanned<Expr>,
 }
 
 #[derive(Clone)]
@@ -12,12 +15,14 @@ pub enum Expr {
     Projection(Projection),
     Binder(Binder),
     Literal(Literal),
+    /// Left by the parser where a syntax error is recovered.
+    Error,
 }
 
 #[derive(Clone)]
 pub struct Binding {
-    pub head: Head,
-    pub expr: Box<Expr>,
+    pub head: Spanned<Head>,
+    pub expr: Box<Spanned<Expr>>,
 }
 
 #[derive(Clone)]
@@ -32,12 +37,12 @@ impl Binder {
 #[derive(Clone)]
 pub enum Head {
     Fun {
-        binder: Binder,
-        args: Pattern,
+        binder: Spanned<Binder>,
+        args: Spanned<Pattern>,
         mask: Mask,
     },
     Pat {
-        pattern: Pattern,
+        pattern: Spanned<Pattern>,
         mask: Mask,
     },
 }
@@ -50,13 +55,15 @@ pub enum Mask {
 
 #[derive(Clone)]
 pub struct Application {
-    func: Box<Expr>,
-    arg: Box<Expr>,
+    func: Box<Spanned<Expr>>,
+    arg: Box<Spanned<Expr>>,
 }
 
 #[derive(Clone)]
 pub enum ControlFlow {
-    Matching(Box<Expr>, Vec<(Pattern, Expr)>),
+    Matching(Box<Spanned<Expr>>, Vec<(Spanned<Pattern>, Spanned<Expr>)>),
+    /// `?? c | t | e`
+    Condition(Box<Spanned<Expr>>, Box<Spanned<Expr>>, Box<Spanned<Expr>>),
     // Enumeration(Expr, Vec<Expr>)
 }
 
@@ -70,41 +77,58 @@ pub enum Block {
 
 #[derive(Clone)]
 pub struct BlockInner<Val> {
-    pub bds: Vec<Binding>,
-    pub vls: Vec<Val>,
+    pub bds: Vec<Spanned<Binding>>,
+    pub vls: Vec<Spanned<Val>>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Pair {
-    pub key: Expr,
-    pub val: Expr,
+    pub key: Spanned<Expr>,
+    pub val: Spanned<Expr>,
 }
 
 #[derive(Clone)]
 pub struct Projection {
-    block: Box<Expr>,
-    binder: Binder,
+    block: Box<Spanned<Expr>>,
+    binder: Spanned<Binder>,
 }
 
 #[derive(Clone)]
 pub enum Literal {
-    Int(u64),
+    Int(BigInt),
     Float(f64),
-    Str(String),
-    Raw(String),
+    Str(Str),
+    Raw(Raw),
+}
+
+/// A string literal, with its escapes decoded into `value`; `raw` keeps
+/// the source form, quotes included.
+#[derive(Clone, PartialEq)]
+pub struct Str {
+    pub value: String,
+    pub raw: String,
+}
+
+/// The content of a raw literal `[|tag| ... |]`, without the delimiters.
+#[derive(Clone, PartialEq)]
+pub struct Raw {
+    pub tag: Option<String>,
+    pub text: String,
 }
 
 #[derive(Clone)]
 pub enum Pattern {
-    Alias(Box<Pattern>, Box<Pattern>),
+    Alias(Box<Spanned<Pattern>>, Box<Spanned<Pattern>>),
     Wild,
     Rest,
     Literal(Literal),
     Binder(Binder),
-    Exposure(Vec<ExposurePattern>),
-    Vector(Vec<Pattern>),
-    Tuple(Vec<Pattern>),
-    HashMap(Vec<(Expr, Pattern)>),
+    Exposure(Vec<Spanned<ExposurePattern>>),
+    Vector(Vec<Spanned<Pattern>>),
+    Tuple(Vec<Spanned<Pattern>>),
+    HashMap(Vec<(Spanned<Expr>, Spanned<Pattern>)>),
+    /// `[a] + bc`
+    Concat(Box<Spanned<Pattern>>, Box<Spanned<Pattern>>),
 }
 
 #[derive(Clone)]
@@ -117,13 +141,13 @@ pub enum ExposurePattern {
 mod construct {
     use super::*;
 
-    impl From<Expr> for Lala {
-        fn from(body: Expr) -> Self { Self { body } }
+    impl From<Spanned<Expr>> for Lala {
+        fn from(body: Spanned<Expr>) -> Self { Self { body } }
     }
 
-    impl From<BlockInner<Expr>> for Lala {
-        fn from(bi: BlockInner<Expr>) -> Self { 
-            Expr::from(Block::from(bi)).into()
+    impl From<Spanned<BlockInner<Expr>>> for Lala {
+        fn from(bi: Spanned<BlockInner<Expr>>) -> Self { 
+            bi.map(|bi| Expr::from(Block::from(bi))).into()
         }
     }
 
@@ -163,21 +187,22 @@ mod construct {
         fn from(lit: Literal) -> Self { Self::Literal(lit) }
     }
 
-    impl From<(Head, Expr)> for Binding {
-        fn from((head, expr): (Head, Expr)) -> Self {
+    impl From<(Spanned<Head>, Spanned<Expr>)> for Binding {
+        fn from((head, expr): (Spanned<Head>, Spanned<Expr>)) -> Self {
             let expr = Box::new(expr);
             Self { head, expr }
         }
     }
-    impl From<(Head, Binding)> for Binding {
-        fn from((head, binding): (Head, Binding)) -> Self {
-            (head, Expr::from(binding)).into()
+    impl From<(Spanned<Head>, Spanned<Binding>)> for Binding {
+        fn from((head, binding): (Spanned<Head>, Spanned<Binding>)) -> Self {
+            (head, binding.map(Expr::from)).into()
         }
     }
-    impl From<Pattern> for Binding {
-        fn from(pattern: Pattern) -> Self {
-            let expr = pattern.clone();
-            (Head::from(pattern), Expr::from(expr)).into()
+    impl From<Spanned<Pattern>> for Binding {
+        fn from(pattern: Spanned<Pattern>) -> Self {
+            let expr = pattern.clone().map(Expr::from);
+            let span = pattern.span();
+            (Node::new(Head::from(pattern), span), expr).into()
         }
     }
 
@@ -187,74 +212,92 @@ mod construct {
         }
     }
 
-    impl From<(Pattern, Mask)> for Head {
-        fn from((pattern, mask): (Pattern, Mask)) -> Self {
+    impl From<(Spanned<Pattern>, Mask)> for Head {
+        fn from((pattern, mask): (Spanned<Pattern>, Mask)) -> Self {
             Self::Pat { pattern, mask }
         }
     }
-    impl From<Pattern> for Head {
-        fn from(pattern: Pattern) -> Self {
+    impl From<Spanned<Pattern>> for Head {
+        fn from(pattern: Spanned<Pattern>) -> Self {
             let mask = Mask::Exposed;
             Self::Pat { pattern, mask }
         }
     }
-    impl From<(Binder, Pattern, Mask)> for Head {
+    impl From<(Spanned<Binder>, Spanned<Pattern>, Mask)> for Head {
         /// Val form
-        fn from((binder, args, mask): (Binder, Pattern, Mask)) -> Self {
+        fn from(
+            (binder, args, mask): (Spanned<Binder>, Spanned<Pattern>, Mask)
+        ) -> Self {
             Self::Fun { binder, args, mask }
         }
     }
-    impl From<(Binder, Vec<Pattern>, Mask)> for Head {
+    impl From<(Spanned<Binder>, Vec<Spanned<Pattern>>, Mask)> for Head {
         /// Val form
-        fn from((binder, args, mask): (Binder, Vec<Pattern>, Mask)) -> Self {
-            let args = Pattern::Vector(args);
+        fn from(
+            (binder, args, mask): (Spanned<Binder>, Vec<Spanned<Pattern>>, Mask)
+        ) -> Self {
+            let span = args.iter()
+                .map(|p| p.span())
+                .reduce(Span::join)
+                .unwrap_or_else(|| binder.span());
+            let args = Node::new(Pattern::Vector(args), span);
             Self::Fun { binder, args, mask }
         }
     }
 
-    impl From<(Expr, Expr)> for Application {
-        fn from((func, arg): (Expr, Expr)) -> Self {
+    impl From<(Spanned<Expr>, Spanned<Expr>)> for Application {
+        fn from((func, arg): (Spanned<Expr>, Spanned<Expr>)) -> Self {
             let func = Box::new(func);
             let arg = Box::new(arg);
             Self { func, arg }
         }
     }
 
-    impl From<(Expr, Vec<(Pattern, Expr)>)> for ControlFlow {
-        fn from((e, branches): (Expr, Vec<(Pattern, Expr)>)) -> Self {
+    impl From<(Spanned<Expr>, Vec<(Spanned<Pattern>, Spanned<Expr>)>)>
+    for ControlFlow {
+        fn from(
+            (e, branches): (Spanned<Expr>, Vec<(Spanned<Pattern>, Spanned<Expr>)>)
+        ) -> Self {
             Self::Matching(Box::new(e), branches)
         }
     }
 
+    impl From<(Spanned<Expr>, Spanned<Expr>, Spanned<Expr>)> for ControlFlow {
+        fn from((c, t, e): (Spanned<Expr>, Spanned<Expr>, Spanned<Expr>)) -> Self {
+            Self::Condition(Box::new(c), Box::new(t), Box::new(e))
+        }
+    }
+
     impl From<BlockInner<Expr>> for Block {
         fn from(bi: BlockInner<Expr>) -> Self { 
             Self::Tuple(bi)
         }
     }
 
-    impl<Val> From<(Vec<Binding>, Vec<Val>)> for BlockInner<Val> {
+    impl<Val> From<(Vec<Spanned<Binding>>, Vec<Spanned<Val>>)>
+    for BlockInner<Val> {
         fn from(
-            (bds, vls): (Vec<Binding>, Vec<Val>)
+            (bds, vls): (Vec<Spanned<Binding>>, Vec<Spanned<Val>>)
         ) -> Self { 
             Self { bds, vls } 
         }
     }
 
-    impl From<(Expr, Expr)> for Pair {
-        fn from((key, val): (Expr, Expr)) -> Self {
+    impl From<(Spanned<Expr>, Spanned<Expr>)> for Pair {
+        fn from((key, val): (Spanned<Expr>, Spanned<Expr>)) -> Self {
             Self { key, val }
         }
     }
 
-    impl From<(Expr, Binder)> for Projection {
-        fn from((e, binder): (Expr, Binder)) -> Self {
+    impl From<(Spanned<Expr>, Spanned<Binder>)> for Projection {
+        fn from((e, binder): (Spanned<Expr>, Spanned<Binder>)) -> Self {
             let block = Box::new(e);
             Self { block, binder }
         }
     }
 
-    impl From<u64> for Literal {
-        fn from(i: u64) -> Self {
+    impl From<BigInt> for Literal {
+        fn from(i: BigInt) -> Self {
             Self::Int (i)
         }
     }
@@ -265,12 +308,12 @@ mod construct {
     }
     impl From<String> for Literal {
         fn from(s: String) -> Self {
-            Self::Str (s)
+            Self::Str (Str { raw: format!("{:?}", s), value: s })
         }
     }
 
-    impl From<(Pattern, Pattern)> for Pattern {
-        fn from((alias, pat): (Pattern, Pattern)) -> Self {
+    impl From<(Spanned<Pattern>, Spanned<Pattern>)> for Pattern {
+        fn from((alias, pat): (Spanned<Pattern>, Spanned<Pattern>)) -> Self {
             Self::Alias(Box::new(alias), Box::new(pat))
         }
     }
@@ -322,6 +365,7 @@ mod print {
                 Self::Projection(p) => write!(f, "{:#?}", p),
                 Self::Binder(e) => write!(f, "{:#?}", e),
                 Self::Literal(e) => write!(f, "{:#?}", e),
+                Self::Error => write!(f, "<error>"),
             }
         }
     }
@@ -373,6 +417,9 @@ mod print {
                         write!(f, "| {:#?} -> {:#?} ", p, e)?;
                     }
                 }
+                ControlFlow::Condition(c, t, e) => {
+                    write!(f, "?? {:#?} | {:#?} | {:#?}", c, t, e)?;
+                }
             }
             write!(f, "")
         }
@@ -416,7 +463,8 @@ mod print {
                     for b in blk.bds.iter() {
                         db.entry(&"", &b);
                     }
-                    for Pair { key, val } in blk.vls.iter() {
+                    let pairs = blk.vls.iter().map(Inner::inner_ref);
+                    for Pair { key, val } in pairs {
                         db.entry(key, val);
                     }
                     db.finish()
@@ -437,11 +485,36 @@ mod print {
             match self {
                 Literal::Int(e) => write!(f, "Int({})", e),
                 Literal::Float(e) => write!(f, "Flt({})", e),
-                Literal::Str(e) => write!(f, "Str({})", e),
-                Literal::Raw(e) => write!(f, "Raw({})", e),
+                Literal::Str(e) => write!(f, "Str({:?})", e),
+                Literal::Raw(e) => write!(f, "Raw({:?})", e),
+            }
+        }
+    }
+
+    impl fmt::Debug for Str {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.raw)
+        }
+    }
+    impl fmt::Display for Str {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.raw)
+        }
+    }
+
+    impl fmt::Debug for Raw {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match &self.tag {
+                Some(tag) => write!(f, "{}| {}", tag, self.text),
+                None => write!(f, "{}", self.text),
             }
         }
     }
+    impl fmt::Display for Raw {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "[|{:?}|]", self)
+        }
+    }
 
     impl fmt::Debug for Pattern {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -454,7 +527,7 @@ mod print {
                 Self::Exposure(ex) => {
                     write!(f, "<")?;
                     write!(f, "{:#?}", DebugVec(
-                        ex.iter().cloned().collect(),
+                        ex.to_vec(),
                         ";"
                     ))?;
                     write!(f, ">")        
@@ -474,6 +547,7 @@ mod print {
                     write!(f, "{:#?}", DebugVec(ps.clone(), ","))?;
                     write!(f, "}}")
                 }
+                Self::Concat(l, r) => write!(f, "{:#?} + {:#?}", l, r),
             }
         }
     }
@@ -1 +1,4 @@
+pub mod infix;
 pub mod lala_ast;
+pub mod parse;
+pub use parse::parse;
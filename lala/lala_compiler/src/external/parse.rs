@@ -0,0 +1,80 @@
+//! Parsing source into the external ast.
+
+use super::lala_ast::*;
+use crate::diagnostic::{parse::*, Diagnostic};
+use crate::lala;
+use crate::lexer::Lexer;
+
+/// Parses the source, recovering from as many syntax errors as possible.
+///
+/// Returns the (possibly partial) ast, which is `None` only if the parser
+/// fails to recover, along with all diagnostics reported.
+pub fn parse(src: &SrcFile) -> (Option<Lala>, Vec<Diagnostic>) {
+    let mut errors = Vec::new();
+    let mut lexer = Lexer::new(&src.text);
+    let res = lala::LalaParser::new().parse(&mut errors, &mut lexer);
+    let mut diags: Vec<Diagnostic> = lexer
+        .take_errors()
+        .into_iter()
+        .map(from_lex_error)
+        .collect();
+    diags.extend(errors.into_iter().map(from_error_recovery));
+    let lala = match res {
+        Ok(lala) => Some(lala),
+        Err(e) => {
+            diags.push(from_parse_error(e));
+            None
+        }
+    };
+    // lexing errors are taken apart from parsing ones
+    diags.sort_by_key(|d| (d.span().is_none(), d.span().map(|s| s.start)));
+    (lala, diags)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn samples() {
+        let dir = concat!(env!("CARGO_MANIFEST_DIR"), "/../sample");
+        let mut parsed = 0;
+        for entry in fs::read_dir(dir).unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_none_or(|ext| ext != "la") {
+                continue
+            }
+            let text = fs::read_to_string(&path).unwrap();
+            let src = SrcFile::new(path.display().to_string(), text);
+            let (lala, diags) = parse(&src);
+            let rendered: String = diags.iter()
+                .map(|d| d.render(&src))
+                .collect();
+            assert!(diags.is_empty(), "{}", rendered);
+            assert!(lala.is_some());
+            parsed += 1;
+        }
+        assert!(parsed > 0);
+    }
+
+    #[test]
+    fn concat_patterns() {
+        let src = SrcFile::new("main.la", "? xs | [x] - xs -> x");
+        let (_, diags) = parse(&src);
+        let msgs: Vec<_> = diags.iter().map(|d| d.render(&src)).collect();
+        assert_eq!(msgs.len(), 1);
+        assert!(msgs[0].contains("expected `+`"), "{}", msgs[0]);
+    }
+
+    #[test]
+    fn unexpected_char() {
+        let src = SrcFile::new("main.la", "~ a = 1 ` 2;\n~ b = ( , );\n");
+        let (lala, diags) = parse(&src);
+        assert!(lala.is_some());
+        let lines: Vec<_> = diags.iter()
+            .map(|d| d.span().unwrap().start.line)
+            .collect();
+        assert_eq!(lines, vec![1, 2]);
+    }
+}
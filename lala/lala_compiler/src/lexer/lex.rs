@@ -0,0 +1,570 @@
+use super::Token;
+use crate::base::*;
+use crate::lala_ast::{Raw, Str};
+use num_bigint::BigInt;
+use std::fmt;
+
+/// Operators, longest first so that the longest one matches.
+const OPERATORS: &[&str] = &[
+    "**", "==", "!=", "<=", ">=", "++", "&&",
+    "+", "-", "*", "/", "%", "^", "!", "@", "#", "$", "&",
+];
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LexErrorKind {
+    UnexpectedChar(char),
+    UnterminatedComment,
+    UnterminatedStr,
+    InvalidEscape(char),
+    InvalidUnicode,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LexError {
+    pub kind: LexErrorKind,
+    pub span: Span,
+}
+
+/// Splits the source into tokens, skipping whitespaces and comments.
+///
+/// Both line comments `// ...` and block comments `/* ... */` are supported;
+/// block comments may nest.
+///
+/// Errors that don't stop the lexing, e.g. invalid escapes in a string or
+/// unexpected characters, are kept aside; see `take_errors`.
+pub struct Lexer<'input> {
+    text: &'input str,
+    loc: Loc,
+    errors: Vec<LexError>,
+    /// Whether the last token may end an operand; see `Token::is_operand`.
+    operand: bool,
+}
+
+impl<'input> Lexer<'input> {
+    pub fn new(text: &'input str) -> Self {
+        let loc = Loc { offset: 0, line: 1, col: 1 };
+        Self { text, loc, errors: Vec::new(), operand: false }
+    }
+
+    /// The errors recovered from so far.
+    pub fn take_errors(&mut self) -> Vec<LexError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    fn rest(&self) -> &'input str {
+        &self.text[self.loc.offset..]
+    }
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+    fn peek_nth(&self, n: usize) -> Option<char> {
+        self.rest().chars().nth(n)
+    }
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.loc.offset += c.len_utf8();
+        if c == '\n' {
+            self.loc.line += 1;
+            self.loc.col = 1;
+        } else {
+            self.loc.col += 1;
+        }
+        Some(c)
+    }
+    fn bump_n(&mut self, n: usize) {
+        for _ in 0..n {
+            self.bump();
+        }
+    }
+    fn bump_while<P>(&mut self, pred: P) -> &'input str
+    where P: Fn(char) -> bool {
+        let start = self.loc.offset;
+        while self.peek().is_some_and(&pred) {
+            self.bump();
+        }
+        &self.text[start..self.loc.offset]
+    }
+    fn error(&self, kind: LexErrorKind, start: Loc) -> LexError {
+        LexError { kind, span: Span::new(start, self.loc) }
+    }
+
+    /// Skips whitespaces and comments.
+    fn trivia(&mut self) -> Result<(), LexError> {
+        loop {
+            self.bump_while(char::is_whitespace);
+            if self.rest().starts_with("//") {
+                self.bump_while(|c| c != '\n');
+            } else if self.rest().starts_with("/*") {
+                self.block_comment()?;
+            } else {
+                return Ok(())
+            }
+        }
+    }
+
+    fn block_comment(&mut self) -> Result<(), LexError> {
+        let start = self.loc;
+        let mut depth = 0;
+        loop {
+            if self.rest().starts_with("/*") {
+                self.bump_n(2);
+                depth += 1;
+            } else if self.rest().starts_with("*/") {
+                self.bump_n(2);
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(())
+                }
+            } else if self.bump().is_none() {
+                return Err(self.error(LexErrorKind::UnterminatedComment, start))
+            }
+        }
+    }
+
+    fn token(&mut self) -> Result<Token, LexError> {
+        let start = self.loc;
+        let c = self.peek().expect("lexing at the end of input");
+        let next = self.peek_nth(1);
+        let tok = match c {
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            '[' => return Ok(self.raw().unwrap_or_else(|| {
+                self.bump();
+                Token::LBracket
+            })),
+            ']' => Token::RBracket,
+            '{' => Token::LBrace,
+            '}' => Token::RBrace,
+            '|' => Token::Bar,
+            ',' => Token::Comma,
+            ';' => Token::Semi,
+            '~' => Token::Tilde,
+            '?' => Token::Question,
+            ':' if next == Some('=') => {
+                self.bump();
+                Token::ColonEq
+            }
+            ':' => Token::Colon,
+            '-' if next == Some('>') => {
+                self.bump();
+                Token::Arrow
+            }
+            '+' | '-' if !self.operand
+                && next.is_some_and(|c| c.is_ascii_digit()) =>
+            {
+                return Ok(self.number())
+            }
+            '.' if next.is_some_and(|c| c.is_ascii_digit()) => {
+                return Ok(self.number())
+            }
+            '.' if next == Some('.') => {
+                self.bump();
+                Token::DotDot
+            }
+            '.' => Token::Dot,
+            '"' => return self.string(),
+            '_' if next.is_some_and(|c| c.is_ascii_alphabetic()) => {
+                return Ok(self.ident())
+            }
+            '_' => Token::Underscore,
+            c if c.is_ascii_alphabetic() => return Ok(self.ident()),
+            c if c.is_ascii_digit() => return Ok(self.number()),
+            _ => {
+                let rest = self.rest();
+                let op = OPERATORS.iter().find(|op| rest.starts_with(*op));
+                if let Some(op) = op {
+                    self.bump_n(op.len());
+                    return Ok(Token::Op(op.to_string()))
+                }
+                match c {
+                    '=' => Token::Eq,
+                    '<' => Token::Lt,
+                    '>' => Token::Gt,
+                    _ => {
+                        self.bump();
+                        let kind = LexErrorKind::UnexpectedChar(c);
+                        return Err(self.error(kind, start))
+                    }
+                }
+            }
+        };
+        self.bump();
+        Ok(tok)
+    }
+
+    fn ident(&mut self) -> Token {
+        let start = self.loc.offset;
+        self.bump();
+        self.bump_while(|c| c == '_' || c.is_ascii_alphanumeric());
+        self.bump_while(|c| c == '\'');
+        Token::Ident(self.text[start..self.loc.offset].to_owned())
+    }
+
+    /// A number literal, optionally signed; integers are unbounded.
+    fn number(&mut self) -> Token {
+        let start = self.loc;
+        let negative = self.peek() == Some('-');
+        if let Some('+') | Some('-') = self.peek() {
+            self.bump();
+        }
+        let rest = self.rest();
+        let is_hex = rest.starts_with("0x") || rest.starts_with("0X");
+        if is_hex && self.peek_nth(2).is_some_and(|c| c.is_ascii_hexdigit()) {
+            self.bump_n(2);
+            let digits = self.bump_while(|c| c.is_ascii_hexdigit());
+            let int = BigInt::parse_bytes(digits.as_bytes(), 16)
+                .expect("lexed as hex digits");
+            return Token::Int(if negative { -int } else { int })
+        }
+        let digit = |c: Option<char>| c.is_some_and(|c| c.is_ascii_digit());
+        let mut is_float = false;
+        self.bump_while(|c| c.is_ascii_digit());
+        if self.peek() == Some('.') && digit(self.peek_nth(1)) {
+            is_float = true;
+            self.bump();
+            self.bump_while(|c| c.is_ascii_digit());
+        }
+        if let Some('e') | Some('E') = self.peek() {
+            let sign = matches!(self.peek_nth(1), Some('+') | Some('-'));
+            let n = if sign { 2 } else { 1 };
+            if digit(self.peek_nth(n)) {
+                is_float = true;
+                self.bump_n(n);
+                self.bump_while(|c| c.is_ascii_digit());
+            }
+        }
+        let text = &self.text[start.offset..self.loc.offset];
+        if is_float {
+            Token::Float(text.parse().expect("lexed as a float"))
+        } else {
+            Token::Int(text.parse().expect("lexed as an integer"))
+        }
+    }
+
+    fn string(&mut self) -> Result<Token, LexError> {
+        let start = self.loc;
+        let mut value = String::new();
+        self.bump();
+        loop {
+            let at = self.loc;
+            match self.bump() {
+                Some('"') => break,
+                Some('\\') if self.peek().is_some() => match self.escape() {
+                    Ok(c) => value.push(c),
+                    Err(kind) => {
+                        // kept as is
+                        value.push_str(&self.text[at.offset..self.loc.offset]);
+                        let err = self.error(kind, at);
+                        self.errors.push(err);
+                    }
+                },
+                Some(c) => value.push(c),
+                None => {
+                    return Err(self.error(LexErrorKind::UnterminatedStr, start))
+                }
+            }
+        }
+        let raw = self.text[start.offset..self.loc.offset].to_owned();
+        Ok(Token::Str(Str { value, raw }))
+    }
+
+    /// Decodes the escape sequence after a `\`.
+    fn escape(&mut self) -> Result<char, LexErrorKind> {
+        let c = match self.bump().expect("escaping at the end of input") {
+            'n' => '\n',
+            'r' => '\r',
+            't' => '\t',
+            '0' => '\0',
+            '\\' => '\\',
+            '"' => '"',
+            '\'' => '\'',
+            'u' => return self.unicode(),
+            c => return Err(LexErrorKind::InvalidEscape(c)),
+        };
+        Ok(c)
+    }
+
+    /// Decodes `{...}` after `\u`, with 1 to 6 hex digits.
+    fn unicode(&mut self) -> Result<char, LexErrorKind> {
+        if self.peek() != Some('{') {
+            return Err(LexErrorKind::InvalidUnicode)
+        }
+        self.bump();
+        let digits = self.bump_while(|c| c.is_ascii_hexdigit());
+        if self.peek() != Some('}') {
+            return Err(LexErrorKind::InvalidUnicode)
+        }
+        self.bump();
+        if digits.is_empty() || digits.len() > 6 {
+            return Err(LexErrorKind::InvalidUnicode)
+        }
+        u32::from_str_radix(digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .ok_or(LexErrorKind::InvalidUnicode)
+    }
+
+    /// A raw literal `[| ... |]` nests and may span lines;
+    /// without a matching `|]`, `[` is merely a bracket.
+    fn raw(&mut self) -> Option<Token> {
+        let rest = self.rest();
+        if !rest.starts_with("[|") {
+            return None
+        }
+        let mut depth = 0;
+        let mut i = 0;
+        let end = loop {
+            let r = &rest[i..];
+            if r.starts_with("[|") {
+                depth += 1;
+                i += 2;
+            } else if r.starts_with("|]") {
+                depth -= 1;
+                i += 2;
+                if depth == 0 {
+                    break i
+                }
+            } else {
+                i += r.chars().next()?.len_utf8();
+            }
+        };
+        let raw = unraw(&rest[2..end - 2]);
+        self.bump_n(rest[..end].chars().count());
+        Some(Token::Raw(raw))
+    }
+}
+
+/// Splits the optional tag from the content of a raw literal, as in
+/// `[|sql| select 1 |]`; a tag must be followed by a whitespace.
+/// Multi-line content is stripped of its common indentation.
+fn unraw(inner: &str) -> Raw {
+    let is_tag = |tag: &str| {
+        let mut cs = tag.chars();
+        cs.next().is_some_and(|c| c.is_ascii_alphabetic())
+            && cs.all(|c| c == '_' || c == '-' || c.is_ascii_alphanumeric())
+    };
+    let (tag, body) = match inner.find('|') {
+        Some(i) if is_tag(&inner[..i])
+            && inner[i + 1..].starts_with(char::is_whitespace) =>
+        {
+            let body = &inner[i + 1..];
+            let body = body.strip_prefix(&[' ', '\t'][..]).unwrap_or(body);
+            (Some(inner[..i].to_owned()), body)
+        }
+        _ => (None, inner),
+    };
+    Raw { tag, text: dedent(body) }
+}
+
+/// Drops the line breaks right after the opening and before the closing
+/// delimiter, then strips the common indentation of the non-blank lines.
+fn dedent(body: &str) -> String {
+    if !body.contains('\n') {
+        return body.to_owned()
+    }
+    let body = body.strip_prefix('\n').unwrap_or(body);
+    let body = match body.rfind('\n') {
+        Some(i) if body[i + 1..].trim().is_empty() => &body[..i],
+        _ => body,
+    };
+    let indent_of = |line: &str| {
+        line.len() - line.trim_start_matches(&[' ', '\t'][..]).len()
+    };
+    let indent = body.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(indent_of)
+        .min()
+        .unwrap_or(0);
+    body.lines()
+        .map(|line| line.get(indent..).unwrap_or_else(|| line.trim()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl<'input> Iterator for Lexer<'input> {
+    type Item = Result<(Loc, Token, Loc), LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Err(e) = self.trivia() {
+                return Some(Err(e))
+            }
+            self.peek()?;
+            let start = self.loc;
+            match self.token() {
+                Ok(tok) => {
+                    self.operand = tok.is_operand();
+                    return Some(Ok((start, tok, self.loc)))
+                }
+                Err(e) => match e.kind {
+                    // skipped, to keep on parsing
+                    LexErrorKind::UnexpectedChar(_) => self.errors.push(e),
+                    _ => {
+                        self.operand = false;
+                        return Some(Err(e))
+                    }
+                },
+            }
+        }
+    }
+}
+
+impl fmt::Display for LexError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            LexErrorKind::UnexpectedChar(c) => {
+                write!(f, "unexpected character `{}`", c)
+            }
+            LexErrorKind::UnterminatedComment => {
+                write!(f, "unterminated block comment")
+            }
+            LexErrorKind::UnterminatedStr => {
+                write!(f, "unterminated string literal")
+            }
+            LexErrorKind::InvalidEscape(c) => {
+                write!(f, "unknown character escape `\\{}`", c.escape_default())
+            }
+            LexErrorKind::InvalidUnicode => {
+                write!(f, "invalid unicode escape")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tokens(text: &str) -> Vec<Token> {
+        Lexer::new(text).map(|t| t.unwrap().1).collect()
+    }
+
+    #[test]
+    fn comments() {
+        let text = "\
+// line comment
+a := /* block /* nested */ comment */ 1; // trailing
+";
+        let expected = vec![
+            Token::Ident("a".into()),
+            Token::ColonEq,
+            Token::Int(1.into()),
+            Token::Semi,
+        ];
+        assert_eq!(tokens(text), expected);
+        let err = Lexer::new("/* /* */").next().unwrap().unwrap_err();
+        assert_eq!(err.kind, LexErrorKind::UnterminatedComment);
+    }
+
+    #[test]
+    fn literals() {
+        let expected = vec![
+            Token::Int(1.into()),
+            Token::Int(0x2f.into()),
+            Token::Float(3.0),
+            Token::Float(0.4),
+            Token::Float(5e1),
+            Token::Str(Str {
+                value: "...\"".into(),
+                raw: r#""...\"""#.into(),
+            }),
+            Token::Raw(Raw { tag: None, text: "jail".into() }),
+        ];
+        let text = r#"1 0x2f 3.0 .4 5e1 "...\"" [|jail|]"#;
+        assert_eq!(tokens(text), expected);
+    }
+
+    #[test]
+    fn signs() {
+        let id: BigInt = "340282366920938463463374607431768211455"
+            .parse()
+            .unwrap();
+        let expected = vec![
+            Token::Int((-1).into()),
+            Token::Comma,
+            Token::Int((-0x2f).into()),
+            Token::Comma,
+            Token::Float(-0.5),
+            Token::Comma,
+            Token::Int(id),
+            Token::Comma,
+            Token::LParen,
+            Token::Ident("a".into()),
+            Token::Op("-".into()),
+            Token::Int(1.into()),
+            Token::Op("+".into()),
+            Token::Int((-1).into()),
+            Token::RParen,
+        ];
+        let text = "\
+-1, -0x2f, -0.5, 0xffffffffffffffffffffffffffffffff, (a -1 + -1)";
+        assert_eq!(tokens(text), expected);
+    }
+
+    #[test]
+    fn escapes() {
+        let text = r#""a\tb\n\"\\\u{3bb}" "\q\u{110000}""#;
+        let mut lexer = Lexer::new(text);
+        let values: Vec<String> = lexer
+            .by_ref()
+            .map(|t| match t.unwrap().1 {
+                Token::Str(s) => s.value,
+                tok => panic!("unexpected token {}", tok),
+            })
+            .collect();
+        assert_eq!(values, vec!["a\tb\n\"\\\u{3bb}", r"\q\u{110000}"]);
+        let errors: Vec<(LexErrorKind, usize, usize)> = lexer
+            .take_errors()
+            .into_iter()
+            .map(|e| (e.kind, e.span.start.col, e.span.end.col))
+            .collect();
+        let expected = vec![
+            (LexErrorKind::InvalidEscape('q'), 22, 24),
+            (LexErrorKind::InvalidUnicode, 24, 34),
+        ];
+        assert_eq!(errors, expected);
+    }
+
+    #[test]
+    fn raws() {
+        let raw = |tag: Option<&str>, text: &str| Token::Raw(Raw {
+            tag: tag.map(Into::into),
+            text: text.into(),
+        });
+        let text = "\
+[| a [|nested|] raw |]
+[|sql| select 1 |]
+[|sh|
+    for f in *.la; do
+        lalac $f
+    done
+|]
+[|x| (x)]
+";
+        let expected = vec![
+            raw(None, " a [|nested|] raw "),
+            raw(Some("sql"), "select 1 "),
+            raw(Some("sh"), "for f in *.la; do\n    lalac $f\ndone"),
+            Token::LBracket,
+            Token::Bar,
+            Token::Ident("x".into()),
+            Token::Bar,
+            Token::LParen,
+            Token::Ident("x".into()),
+            Token::RParen,
+            Token::RBracket,
+        ];
+        assert_eq!(tokens(text), expected);
+    }
+
+    #[test]
+    fn samples() {
+        let samples = [
+            include_str!("../../../sample/oo.la"),
+            include_str!("../../../sample/qsort.la"),
+        ];
+        for sample in samples.iter() {
+            assert!(Lexer::new(sample).all(|t| t.is_ok()));
+        }
+    }
+}
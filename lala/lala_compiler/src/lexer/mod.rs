@@ -0,0 +1,7 @@
+//! The hand-written lexer, fed to the parser as an external lexer.
+
+pub mod token;
+pub mod lex;
+
+pub use token::Token;
+pub use lex::{LexError, LexErrorKind, Lexer};
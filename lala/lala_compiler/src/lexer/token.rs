@@ -0,0 +1,82 @@
+use crate::lala_ast::{Raw, Str};
+use num_bigint::BigInt;
+use std::fmt;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Token {
+    // Delimiters
+    LParen,
+    RParen,
+    LBracket,
+    RBracket,
+    LBrace,
+    RBrace,
+    // Punctuations
+    Bar,
+    Comma,
+    Semi,
+    Colon,
+    ColonEq,
+    Eq,
+    Dot,
+    DotDot,
+    Underscore,
+    Tilde,
+    Question,
+    Arrow,
+    Lt,
+    Gt,
+    /// Any other operator symbol, e.g. `+` or `**`.
+    Op(String),
+    // Identifiers and literals
+    Ident(String),
+    Int(BigInt),
+    Float(f64),
+    Str(Str),
+    Raw(Raw),
+}
+
+impl Token {
+    /// Whether the token may end an operand, after which a sign
+    /// can't start a number literal.
+    pub fn is_operand(&self) -> bool {
+        matches!(self,
+            Token::RParen | Token::RBracket | Token::RBrace
+            | Token::Underscore | Token::Ident(_) | Token::Int(_)
+            | Token::Float(_) | Token::Str(_) | Token::Raw(_)
+        )
+    }
+}
+
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LParen => write!(f, "("),
+            Token::RParen => write!(f, ")"),
+            Token::LBracket => write!(f, "["),
+            Token::RBracket => write!(f, "]"),
+            Token::LBrace => write!(f, "{{"),
+            Token::RBrace => write!(f, "}}"),
+            Token::Bar => write!(f, "|"),
+            Token::Comma => write!(f, ","),
+            Token::Semi => write!(f, ";"),
+            Token::Colon => write!(f, ":"),
+            Token::ColonEq => write!(f, ":="),
+            Token::Eq => write!(f, "="),
+            Token::Dot => write!(f, "."),
+            Token::DotDot => write!(f, ".."),
+            Token::Underscore => write!(f, "_"),
+            Token::Tilde => write!(f, "~"),
+            Token::Question => write!(f, "?"),
+            Token::Arrow => write!(f, "->"),
+            Token::Lt => write!(f, "<"),
+            Token::Gt => write!(f, ">"),
+            Token::Op(s) => write!(f, "{}", s),
+            Token::Ident(s) => write!(f, "{}", s),
+            Token::Int(i) => write!(f, "{}", i),
+            Token::Float(x) => write!(f, "{}", x),
+            Token::Str(s) => write!(f, "{}", s),
+            Token::Raw(r) => write!(f, "{}", r),
+        }
+    }
+}
@@ -1,10 +1,13 @@
-mod external;
+pub mod base;
+pub mod diagnostic;
+pub mod external;
+pub mod lexer;
 mod resolve;
 use external::lala_ast;
 
 
 #[macro_use] extern crate lalrpop_util;
-lalrpop_mod!(pub lala);
+lalrpop_mod!(#[allow(clippy::all)] pub lala);
 
 #[cfg(test)]
 mod tests {
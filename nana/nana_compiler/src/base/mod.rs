@@ -1,3 +1,8 @@
+mod span;
+pub use span::*;
+
+use num_bigint::BigInt;
+
 pub trait Inner {
     type Target;
     fn inner(self) -> Self::Target;
@@ -5,7 +10,20 @@ pub trait Inner {
     fn inner_mut(&mut self) -> &mut Self::Target;
 }
 
+#[derive(Clone)]
 pub struct Node<T, Attr> (T, Attr);
+impl<T, Attr> Node<T, Attr> {
+    pub fn new(t: T, attr: Attr) -> Self {
+        Self (t, attr)
+    }
+    pub fn attr(&self) -> &Attr {
+        &self.1
+    }
+    pub fn map<U, F>(self, f: F) -> Node<U, Attr>
+    where F: FnOnce(T) -> U {
+        Node (f(self.0), self.1)
+    }
+}
 impl<T, Attr> Inner for Node<T, Attr> {
     type Target = T;
     fn inner(self) -> Self::Target {
@@ -19,12 +37,35 @@ impl<T, Attr> Inner for Node<T, Attr> {
     }
 }
 
+/// A node attached with its source span.
+pub type Spanned<T> = Node<T, Span>;
+impl<T> Spanned<T> {
+    pub fn span(&self) -> Span {
+        self.1
+    }
+}
+
 #[derive(Clone)]
 pub enum Literal {
-    Int(u64),
+    Int(BigInt),
     Float(f64),
-    Str(String),
-    Raw(String),
+    Str(Str),
+    Raw(Raw),
+}
+
+/// A string literal, with its escapes decoded into `value`; `raw` keeps
+/// the source form, quotes included.
+#[derive(Clone, PartialEq)]
+pub struct Str {
+    pub value: String,
+    pub raw: String,
+}
+
+/// The content of a raw literal `[|tag| ... |]`, without the delimiters.
+#[derive(Clone, PartialEq)]
+pub struct Raw {
+    pub tag: Option<String>,
+    pub text: String,
 }
 
 #[derive(Clone)]
@@ -34,14 +75,17 @@ impl Binder {
         let Binder(s) = self;
         s
     }
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
 }
 
 /// Constructing Ast with From trait
 mod construct {
     use super::*;
 
-    impl From<u64> for Literal {
-        fn from(i: u64) -> Self {
+    impl From<BigInt> for Literal {
+        fn from(i: BigInt) -> Self {
             Self::Int (i)
         }
     }
@@ -52,7 +96,7 @@ mod construct {
     }
     impl From<String> for Literal {
         fn from(s: String) -> Self {
-            Self::Str (s)
+            Self::Str (Str { raw: format!("{:?}", s), value: s })
         }
     }
 
@@ -69,17 +113,50 @@ mod print {
     use super::*;
     use std::fmt;
 
+    /// Attributes are transparent when printing.
+    impl<T, Attr> fmt::Debug for Node<T, Attr>
+    where T: fmt::Debug {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            self.0.fmt(f)
+        }
+    }
+
     impl fmt::Debug for Literal {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             match self {
                 Literal::Int(e) => write!(f, "Int({})", e),
                 Literal::Float(e) => write!(f, "Flt({})", e),
-                Literal::Str(e) => write!(f, "Str({})", e),
-                Literal::Raw(e) => write!(f, "Raw({})", e),
+                Literal::Str(e) => write!(f, "Str({:?})", e),
+                Literal::Raw(e) => write!(f, "Raw({:?})", e),
             }
         }
     }
 
+    impl fmt::Debug for Str {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.raw)
+        }
+    }
+    impl fmt::Display for Str {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self.raw)
+        }
+    }
+
+    impl fmt::Debug for Raw {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match &self.tag {
+                Some(tag) => write!(f, "{}| {}", tag, self.text),
+                None => write!(f, "{}", self.text),
+            }
+        }
+    }
+    impl fmt::Display for Raw {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "[|{:?}|]", self)
+        }
+    }
+
     impl fmt::Debug for Binder {
         fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
             write!(f, "{}", self.0)
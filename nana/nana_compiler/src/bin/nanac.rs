@@ -1,44 +1,228 @@
 use std::io::Read;
 
 use nana_compiler::{
-    nana,
+    base::SrcFile,
+    external::parse,
+    resolve::resolve,
+    depend::depend,
+    diagnostic::emit::from_emit_error,
+    emit::{css, html, json, toml, yaml, EmitError},
+    emit::{Css, Html, Json, Toml, Yaml},
+    eval::{eval_with, Config, Value},
+    import::{import, Format, Import},
+    diagnostic::import::from_import_error,
+    diagnostic::{Diagnostic, Level},
+    visible::visible,
     Flatten,
 };
 
 fn main() -> anyhow::Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
 
-    if std::env::args().len() > 1 {
+    if args.iter().any(|arg| arg == "--cases") {
         for (i, code) in code_base().iter().enumerate() {
             println!(">>>>>> Parsing: Case {} >>>>>>", i);
-            parse_nana(code);
+            let _ = parse_nana(&SrcFile::new(format!("case {}", i), code));
         }
         return Ok(())
     }
 
+    // `nanac eval [--threads N] [file]...` prints the value of each file;
+    // `nanac emit --target T [options] [file]...` emits it in T;
+    // `nanac import --from F [--hoist [--size N]] [file]...` writes each
+    // document of F as nana; diagnostics go to stderr, and it exits with 1
+    // if any file fails
+    let mode = args.first().cloned().unwrap_or_default();
+    let run: Box<dyn Fn(&SrcFile) -> bool> = match mode.as_str() {
+        "eval" | "emit" => {
+            args.remove(0);
+            let mut config = Config::default();
+            if let Some(threads) = option(&mut args, "--threads")? {
+                config.threads = threads.parse()?;
+            }
+            if mode == "eval" {
+                Box::new(move |src| {
+                    emit_nana(src, &config, &|v| Ok(v.to_string()))
+                })
+            } else {
+                let emit = emitter(&mut args)?;
+                Box::new(move |src| emit_nana(src, &config, &*emit))
+            }
+        }
+        "import" => {
+            args.remove(0);
+            let format: Format = option(&mut args, "--from")?
+                .ok_or_else(|| anyhow::anyhow!("`import` takes a `--from`"))?
+                .parse()
+                .map_err(anyhow::Error::msg)?;
+            let hoist = switch(&mut args, "--hoist");
+            let mut config = Import { hoist, ..Import::default() };
+            if let Some(n) = option(&mut args, "--size")? {
+                config.size = n.parse()?;
+            }
+            Box::new(move |src| match import(src, format, &config) {
+                Ok(out) => {
+                    println!("{}", out);
+                    true
+                }
+                Err(err) => report(src, &[from_import_error(err)]),
+            })
+        }
+        _ => Box::new(parse_nana),
+    };
+
     // get the file[s] and combine them in simultaneity, and then...
 
-    let mut buf = String::new();
-    std::io::stdin().read_to_string(&mut buf)?;
+    let mut ok = true;
+    if args.is_empty() {
+        let mut buf = String::new();
+        std::io::stdin().read_to_string(&mut buf)?;
+        ok &= run(&SrcFile::new("<stdin>", buf));
+    }
+    for path in args {
+        let text = std::fs::read_to_string(&path)?;
+        ok &= run(&SrcFile::new(path, text));
+    }
 
-    parse_nana(&buf);
-    
+    if !ok {
+        std::process::exit(1)
+    }
     Ok(())
 }
 
+/// Prints the diagnostics to stderr, telling if none is an error.
+fn report(src: &SrcFile, diags: &[Diagnostic]) -> bool {
+    for diag in diags.iter() {
+        eprintln!("{}", diag.render(src));
+    }
+    diags.iter().all(|d| d.level != Level::Error)
+}
+
 
-fn parse_nana(code: &String) {
-    let res = nana::NanaParser::new().parse(code);
-    match res {
-        Ok(nana) => {
-            // println!("{:#?}", nana);
-            let nana = nana.flatten();
-            println!("{:#?}", nana);
+type Emit = dyn Fn(&Value) -> Result<String, EmitError>;
+
+/// Takes the value of the option out of the args, if given.
+fn option(
+    args: &mut Vec<String>, name: &str
+) -> anyhow::Result<Option<String>> {
+    let i = match args.iter().position(|arg| arg == name) {
+        Some(i) => i,
+        None => return Ok(None),
+    };
+    if i + 1 >= args.len() {
+        anyhow::bail!("`{}` takes a value", name)
+    }
+    let value = args.remove(i + 1);
+    args.remove(i);
+    Ok(Some(value))
+}
+
+/// Takes the switch out of the args, telling if given.
+fn switch(args: &mut Vec<String>, name: &str) -> bool {
+    let len = args.len();
+    args.retain(|arg| arg != name);
+    args.len() < len
+}
+
+/// The emitter of the target, configured by the options:
+/// - json: `--compact`, `--unit E`, `--nil E`, `--null E` where E is
+///   `null`, `array` or `object`;
+/// - yaml: `--no-anchors`, and those of empty blocks as json;
+/// - toml: `--loose` for arrays of mixed kinds, `--inline N` for the
+///   longest inline table;
+/// - html: `--no-doctype`;
+/// - css: `--compact`.
+fn emitter(args: &mut Vec<String>) -> anyhow::Result<Box<Emit>> {
+    let target = option(args, "--target")?
+        .ok_or_else(|| anyhow::anyhow!("`emit` takes a `--target`"))?;
+    match target.as_str() {
+        "json" => {
+            let pretty = !switch(args, "--compact");
+            let mut config = Json { pretty, ..Json::default() };
+            if let Some(e) = option(args, "--unit")? {
+                config.unit = e.parse().map_err(anyhow::Error::msg)?;
+            }
+            if let Some(e) = option(args, "--nil")? {
+                config.nil = e.parse().map_err(anyhow::Error::msg)?;
+            }
+            if let Some(e) = option(args, "--null")? {
+                config.null = e.parse().map_err(anyhow::Error::msg)?;
+            }
+            Ok(Box::new(move |v| json(v, &config)))
+        }
+        "yaml" => {
+            let anchors = !switch(args, "--no-anchors");
+            let mut config = Yaml { anchors, ..Yaml::default() };
+            if let Some(e) = option(args, "--unit")? {
+                config.unit = e.parse().map_err(anyhow::Error::msg)?;
+            }
+            if let Some(e) = option(args, "--nil")? {
+                config.nil = e.parse().map_err(anyhow::Error::msg)?;
+            }
+            if let Some(e) = option(args, "--null")? {
+                config.null = e.parse().map_err(anyhow::Error::msg)?;
+            }
+            Ok(Box::new(move |v| yaml(v, &config)))
+        }
+        "toml" => {
+            let strict = !switch(args, "--loose");
+            let mut config = Toml { strict, ..Toml::default() };
+            if let Some(n) = option(args, "--inline")? {
+                config.inline = n.parse()?;
+            }
+            Ok(Box::new(move |v| toml(v, &config)))
         }
-        Err(e) => {
-            println!("Error: {}", e);
+        "html" => {
+            let doctype = !switch(args, "--no-doctype");
+            Ok(Box::new(move |v| html(v, &Html { doctype })))
         }
+        "css" => {
+            let pretty = !switch(args, "--compact");
+            Ok(Box::new(move |v| css(v, &Css { pretty })))
+        }
+        _ => anyhow::bail!("unknown target `{}`", target),
+    }
+}
+
+
+fn emit_nana(src: &SrcFile, config: &Config, emit: &Emit) -> bool {
+    let (res, diags) = parse(src);
+    let nana = match (res, report(src, &diags)) {
+        (Some(nana), true) => nana,
+        _ => return false,
+    };
+    match eval_with(nana.flatten(), config) {
+        Ok(value) => match emit(&value) {
+            Ok(out) => {
+                println!("{}", out);
+                true
+            }
+            Err(err) => report(src, &[from_emit_error(err)]),
+        },
+        Err(diags) => report(src, &diags),
+    }
+}
+
+
+fn parse_nana(src: &SrcFile) -> bool {
+    let (res, diags) = parse(src);
+    let mut ok = report(src, &diags);
+    if let (Some(nana), true) = (res, ok) {
+        // println!("{:#?}", nana);
+        let nana = nana.flatten();
+        println!("{:#?}", nana);
+        let diags = match resolve(nana) {
+            Ok(nana) => {
+                let mut diags = visible(&nana).err().unwrap_or_default();
+                diags.extend(depend(&nana).1);
+                diags
+            }
+            Err(diags) => diags,
+        };
+        ok = report(src, &diags);
     }
     println!("{}", "=".repeat(80));
+    ok
 }
 
 
@@ -83,6 +267,24 @@ curry := |a, b| (
 curry' := |a, b| (
     ((|| ( |c| (((1))))))
 );
+    "#),
+    format!("{}", r#"
+[
+    tuple x y := (x, y);
+    swap (x, y) = tuple y x;
+    swap (1, 2)
+]
+    "#),
+    format!("{}", r#"
+empty xs := ? xs
+    | [] -> 1
+    | [_, ..] -> 0;
+    "#),
+    format!("{}", r#"
+{
+    zero := 0;
+    "result": zero + 1, "null": zero,
+}
     "#),
     ]
 } 
@@ -0,0 +1,381 @@
+//! Dependency of the bindings of `{}` blocks.
+//!
+//! A `{}` binding may refer to any sibling, so the siblings it refers to must
+//! be evaluated before it. Only strict references count: those under a gate,
+//! e.g. in a function body, are deferred until the gate is applied, which
+//! is why recursive functions don't form a cycle.
+
+use crate::diagnostic::{depend::from_depend_error, Diagnostic};
+use crate::resolve::ast::*;
+use std::collections::HashMap;
+
+/// A reference from one binding to a sibling.
+#[derive(Clone, Debug)]
+pub struct Edge {
+    /// The binding referring, indexed in the binder space.
+    pub from: usize,
+    /// The binding referred to.
+    pub to: usize,
+    /// The binder first referred to by it.
+    pub binder: Binder,
+    /// Where it's first referred to.
+    pub span: Span,
+}
+
+/// The graph of the binder space of a `{}` block.
+#[derive(Clone, Debug)]
+pub struct Graph {
+    /// The span of the block.
+    pub span: Span,
+    /// The spans of the patterns of the bindings.
+    pub nodes: Vec<Span>,
+    /// The binders bound by each binding.
+    pub names: Vec<Vec<Binder>>,
+    /// At most one from a binding to another.
+    pub edges: Vec<Edge>,
+}
+
+#[derive(Clone, Debug)]
+pub enum DependError {
+    /// Edges forming a cycle, each referring to the next; a warning.
+    Cycle(Vec<Edge>),
+}
+
+/// Builds the graphs of all `{}` blocks, outer ones first, along with
+/// warnings of the cycles found.
+pub fn depend(nana: &Nana) -> (Vec<Graph>, Vec<Diagnostic>) {
+    let mut graphs = Vec::new();
+    gated_block(nana.body.inner_ref(), &mut graphs);
+    let warnings = graphs.iter()
+        .filter_map(|g| g.order().err())
+        .flatten()
+        .map(DependError::Cycle)
+        .map(from_depend_error)
+        .collect();
+    (graphs, warnings)
+}
+
+impl Graph {
+    pub fn new(span: Span, bds: &[Spanned<Abstraction>]) -> Self {
+        let mut owner = HashMap::new();
+        let names = bds.iter().enumerate().map(|(i, abs)| {
+            let mut defs = Vec::new();
+            pattern_defs(abs.inner_ref().trace.inner_ref(), &mut defs);
+            defs.into_iter().map(|def| {
+                owner.insert(def.id, i);
+                def.binder.clone()
+            }).collect()
+        }).collect();
+        let mut edges = Vec::new();
+        for (from, abs) in bds.iter().enumerate() {
+            let mut uses = Vec::new();
+            strict_uses(abs.inner_ref().src.inner_ref(), &mut uses);
+            for (u, span) in uses {
+                let to = match owner.get(&u.def) {
+                    Some(&to) => to,
+                    None => continue,
+                };
+                if edges.iter().any(|e: &Edge| e.from == from && e.to == to) {
+                    continue
+                }
+                let binder = u.binder.clone();
+                edges.push(Edge { from, to, binder, span });
+            }
+        }
+        let nodes = bds.iter()
+            .map(|abs| abs.inner_ref().trace.span())
+            .collect();
+        Self { span, nodes, names, edges }
+    }
+
+    /// The order to evaluate the bindings in, each after those it refers to,
+    /// and otherwise in the order written; or all the cycles that prevent it.
+    pub fn order(&self) -> Result<Vec<usize>, Vec<Vec<Edge>>> {
+        let mut visit = Visit {
+            graph: self,
+            state: vec![State::Unvisited; self.nodes.len()],
+            path: Vec::new(),
+            order: Vec::new(),
+            cycles: Vec::new(),
+        };
+        for node in 0..self.nodes.len() {
+            visit.node(node);
+        }
+        if visit.cycles.is_empty() {
+            Ok(visit.order)
+        } else {
+            Err(visit.cycles)
+        }
+    }
+
+    /// Renders the graph in the dot language of Graphviz, each binding
+    /// pointing to those it refers to.
+    pub fn dot(&self) -> String {
+        let mut out = String::from("digraph {\n");
+        for (i, names) in self.names.iter().enumerate() {
+            let names: Vec<_> = names.iter().map(Binder::as_str).collect();
+            out += &format!("    {} [label=\"{}\"];\n", i, names.join(", "));
+        }
+        for edge in self.edges.iter() {
+            out += &format!("    {} -> {};\n", edge.from, edge.to);
+        }
+        out += "}\n";
+        out
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum State {
+    Unvisited,
+    Visiting,
+    Visited,
+}
+
+/// Depth first search, in post order.
+struct Visit<'a> {
+    graph: &'a Graph,
+    state: Vec<State>,
+    /// Edges taken to the node being visited.
+    path: Vec<&'a Edge>,
+    order: Vec<usize>,
+    cycles: Vec<Vec<Edge>>,
+}
+
+impl<'a> Visit<'a> {
+    fn node(&mut self, node: usize) {
+        if self.state[node] != State::Unvisited {
+            return
+        }
+        self.state[node] = State::Visiting;
+        let graph = self.graph;
+        for edge in graph.edges.iter().filter(|e| e.from == node) {
+            match self.state[edge.to] {
+                State::Unvisited => {
+                    self.path.push(edge);
+                    self.node(edge.to);
+                    self.path.pop();
+                }
+                State::Visiting => {
+                    let start = self.path.iter()
+                        .position(|e| e.from == edge.to)
+                        .unwrap_or(self.path.len());
+                    let mut cycle: Vec<_> = self.path[start..].iter()
+                        .map(|&e| e.clone())
+                        .collect();
+                    cycle.push(edge.clone());
+                    self.cycles.push(cycle);
+                }
+                State::Visited => (),
+            }
+        }
+        self.state[node] = State::Visited;
+        self.order.push(node);
+    }
+}
+
+fn pattern_defs<'a>(pat: &'a Pattern, defs: &mut Vec<&'a Def>) {
+    match pat {
+        Pattern::Alias(al, p) => {
+            pattern_defs(al.inner_ref(), defs);
+            pattern_defs(p.inner_ref(), defs);
+        }
+        Pattern::Wild | Pattern::Forall | Pattern::Literal(_) => (),
+        Pattern::Binder(def) => defs.push(def),
+        Pattern::Exposure(ex) => {
+            for ex in ex.iter() {
+                match ex.inner_ref() {
+                    ExposurePattern::Binder(def) => defs.push(def),
+                    ExposurePattern::All(def) => defs.push(def),
+                }
+            }
+        }
+        Pattern::Tuple(ps) | Pattern::List(ps) => {
+            for p in ps.iter() {
+                pattern_defs(p.inner_ref(), defs);
+            }
+        }
+        Pattern::Map(ps) => {
+            for p in ps.iter() {
+                pattern_defs(p.inner_ref().val.inner_ref(), defs);
+            }
+        }
+    }
+}
+
+/// The uses evaluated along with the gated block, unless it's gated.
+fn strict_uses<'a>(g: &'a GatedBlock, uses: &mut Vec<(&'a Use, Span)>) {
+    if !g.traces.is_empty() {
+        return
+    }
+    let (bds, vls, pairs) = g.block.inner_ref().spaces();
+    for abs in bds.iter() {
+        pattern_uses(abs.inner_ref().trace.inner_ref(), uses);
+        strict_uses(abs.inner_ref().src.inner_ref(), uses);
+    }
+    for e in vls.iter() {
+        expr_uses(e, uses);
+    }
+    for pair in pairs.iter() {
+        expr_uses(&pair.inner_ref().key, uses);
+        expr_uses(&pair.inner_ref().val, uses);
+    }
+}
+
+/// The uses in the keys of map patterns.
+fn pattern_uses<'a>(pat: &'a Pattern, uses: &mut Vec<(&'a Use, Span)>) {
+    match pat {
+        Pattern::Alias(al, p) => {
+            pattern_uses(al.inner_ref(), uses);
+            pattern_uses(p.inner_ref(), uses);
+        }
+        Pattern::Tuple(ps) | Pattern::List(ps) => {
+            for p in ps.iter() {
+                pattern_uses(p.inner_ref(), uses);
+            }
+        }
+        Pattern::Map(ps) => {
+            for p in ps.iter() {
+                expr_uses(&p.inner_ref().key, uses);
+                pattern_uses(p.inner_ref().val.inner_ref(), uses);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn expr_uses<'a>(e: &'a Spanned<Expr>, uses: &mut Vec<(&'a Use, Span)>) {
+    match e.inner_ref() {
+        Expr::Literal(_) => (),
+        Expr::Binder(u) => uses.push((u, e.span())),
+        Expr::GatedBlock(g) => strict_uses(g, uses),
+        Expr::Application(f, a) => {
+            expr_uses(f, uses);
+            expr_uses(a, uses);
+        }
+        Expr::Projection(e, _) => expr_uses(e, uses),
+        Expr::Matching(e, bs) => {
+            expr_uses(e, uses);
+            for b in bs.iter() {
+                pattern_uses(b.inner_ref().pattern.inner_ref(), uses);
+                expr_uses(&b.inner_ref().body, uses);
+            }
+        }
+    }
+}
+
+/// Collects the graphs of all `{}` blocks within.
+fn gated_block(g: &GatedBlock, graphs: &mut Vec<Graph>) {
+    let (bds, vls, pairs) = g.block.inner_ref().spaces();
+    if let Block::Set(_, _) | Block::Map(_, _) = g.block.inner_ref() {
+        graphs.push(Graph::new(g.block.span(), bds));
+    }
+    for abs in bds.iter() {
+        pattern(abs.inner_ref().trace.inner_ref(), graphs);
+        gated_block(abs.inner_ref().src.inner_ref(), graphs);
+    }
+    for e in vls.iter() {
+        expr(e.inner_ref(), graphs);
+    }
+    for pair in pairs.iter() {
+        expr(pair.inner_ref().key.inner_ref(), graphs);
+        expr(pair.inner_ref().val.inner_ref(), graphs);
+    }
+}
+
+fn pattern(pat: &Pattern, graphs: &mut Vec<Graph>) {
+    match pat {
+        Pattern::Alias(al, p) => {
+            pattern(al.inner_ref(), graphs);
+            pattern(p.inner_ref(), graphs);
+        }
+        Pattern::Tuple(ps) | Pattern::List(ps) => {
+            for p in ps.iter() {
+                pattern(p.inner_ref(), graphs);
+            }
+        }
+        Pattern::Map(ps) => {
+            for p in ps.iter() {
+                expr(p.inner_ref().key.inner_ref(), graphs);
+                pattern(p.inner_ref().val.inner_ref(), graphs);
+            }
+        }
+        _ => (),
+    }
+}
+
+fn expr(e: &Expr, graphs: &mut Vec<Graph>) {
+    match e {
+        Expr::Literal(_) | Expr::Binder(_) => (),
+        Expr::GatedBlock(g) => gated_block(g, graphs),
+        Expr::Application(f, a) => {
+            expr(f.inner_ref(), graphs);
+            expr(a.inner_ref(), graphs);
+        }
+        Expr::Projection(e, _) => expr(e.inner_ref(), graphs),
+        Expr::Matching(e, bs) => {
+            expr(e.inner_ref(), graphs);
+            for b in bs.iter() {
+                pattern(b.inner_ref().pattern.inner_ref(), graphs);
+                expr(b.inner_ref().body.inner_ref(), graphs);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::Level;
+    use crate::external::parse;
+    use crate::resolve::resolve;
+    use crate::Flatten;
+
+    fn graphs(text: &str) -> (Vec<Graph>, Vec<Diagnostic>) {
+        let src = SrcFile::new("main.na", text);
+        let (nana, diags) = parse(&src);
+        assert!(diags.is_empty());
+        depend(&resolve(nana.unwrap().flatten()).unwrap())
+    }
+
+    #[test]
+    fn ordering() {
+        let (graphs, warnings) = graphs(
+            "{ two = one + 1; one = zero + 1; zero = 0; fact n = fact n; two }"
+        );
+        assert!(warnings.is_empty());
+        assert_eq!(graphs.len(), 1);
+        assert_eq!(graphs[0].order().unwrap(), vec![2, 1, 0, 3]);
+    }
+
+    #[test]
+    fn cycles() {
+        let (_, diags) = graphs(
+            "{ undone := undone; a = (c, 1); b = a; c = [x = b; x]; }"
+        );
+        assert!(diags.iter().all(|d| d.level == Level::Warning));
+        let notes: Vec<_> = diags.iter().map(|d| d.notes[0].clone()).collect();
+        assert_eq!(notes, vec![
+            "cycle: `undone` -> `undone`",
+            "cycle: `a` -> `c` -> `b` -> `a`",
+        ]);
+    }
+    #[test]
+    fn duplicates() {
+        let (graphs, diags) = graphs("{ a = (b, b + b, c); b = a; c = 1; }");
+        assert_eq!(diags.len(), 1);
+        let edges: Vec<_> = graphs[0].edges.iter()
+            .map(|e| (e.from, e.to, e.span.start.col))
+            .collect();
+        assert_eq!(edges, vec![(0, 1, 8), (0, 2, 18), (1, 0, 26)]);
+        assert_eq!(graphs[0].dot(), "\
+digraph {
+    0 [label=\"a\"];
+    1 [label=\"b\"];
+    2 [label=\"c\"];
+    0 -> 1;
+    0 -> 2;
+    1 -> 0;
+}
+");
+    }
+}
@@ -0,0 +1,36 @@
+//! Reporting errors of the dependency analysis.
+
+use super::Diagnostic;
+use crate::depend::graph::DependError;
+
+pub fn from_depend_error(err: DependError) -> Diagnostic {
+    match err {
+        DependError::Cycle(edges) => {
+            // each edge refers to the binding the next one is from
+            let last = edges.last().expect("empty cycle");
+            let mut names = vec![format!("`{}`", last.binder.as_str())];
+            names.extend(
+                edges.iter().map(|e| format!("`{}`", e.binder.as_str()))
+            );
+            let msg = if edges.len() == 1 {
+                format!("{} depends on itself", names[0])
+            } else {
+                format!("{} depends on itself through a cycle", names[0])
+            };
+            // harmless unless demanded, as bindings are evaluated lazily
+            let mut diag = Diagnostic::warning(msg);
+            for (i, edge) in edges.iter().enumerate() {
+                let label = format!(
+                    "{} refers to {} here", names[i], names[i + 1]
+                );
+                diag = if i == 0 {
+                    diag.with_primary(edge.span, label)
+                } else {
+                    diag.with_secondary(edge.span, label)
+                };
+            }
+            diag.with_note(format!("cycle: {}", names.join(" -> ")))
+                .with_note("evaluating any of them would fail")
+        }
+    }
+}
@@ -0,0 +1,39 @@
+//! Reporting errors of emitting values.
+
+use super::Diagnostic;
+use crate::emit::EmitError;
+
+pub fn from_emit_error(err: EmitError) -> Diagnostic {
+    let (diag, path, span) = match err {
+        EmitError::Unsupported { target, kind, path, span } => {
+            let diag = Diagnostic::error(format!("{} has no {}", kind, target));
+            (diag, Some(path), span)
+        }
+        EmitError::Key { target, kind, path, span } => {
+            let msg = format!("{} can't be a key in {}", kind, target);
+            (Diagnostic::error(msg), Some(path), span)
+        }
+        EmitError::Name { target, name, path, span } => {
+            let msg = format!("`{}` is not a valid name in {}", name, target);
+            (Diagnostic::error(msg), Some(path), span)
+        }
+        EmitError::Mixed { kinds: (a, b), path, span } => {
+            let msg = format!("array of {} and {}", a, b);
+            let diag = Diagnostic::error(msg)
+                .with_note("arrays in strict toml are of values of a kind");
+            (diag, Some(path), span)
+        }
+        EmitError::Top { target, kind, span } => {
+            let msg = format!("{} can't be a document of {}", kind, target);
+            (Diagnostic::error(msg), None, span)
+        }
+    };
+    let diag = match span {
+        Some(span) => diag.with_primary(span, "evaluated from here"),
+        None => diag,
+    };
+    match path {
+        Some(path) => diag.with_note(format!("found at {}", path)),
+        None => diag,
+    }
+}
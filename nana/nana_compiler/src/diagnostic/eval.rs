@@ -0,0 +1,56 @@
+//! Reporting errors of the evaluation.
+
+use super::Diagnostic;
+use crate::eval::eval::EvalError;
+
+/// Errors of several values are reported by the first of them; see
+/// `EvalError::flatten` to report all of them.
+pub fn from_eval_error(err: EvalError) -> Diagnostic {
+    match err {
+        EvalError::NotFunction { kind, span } => {
+            Diagnostic::error(format!("{} can't be applied", kind))
+                .with_primary(span, "applied here")
+                .with_note("only closures and operators can be applied")
+        }
+        EvalError::NoMatch { kind, span } => {
+            Diagnostic::error(format!("no pattern matches the {}", kind))
+                .with_primary(span, "matched here")
+        }
+        EvalError::NotExposed { name, span } => {
+            Diagnostic::error(format!("binder `{}` is not exposed", name))
+                .with_primary(span, "projected here")
+        }
+        EvalError::Cycle(path) => {
+            let (name, span) = path.last().expect("empty cycle");
+            let mut diag = Diagnostic::error(
+                format!("`{}` depends on itself", name)
+            ).with_primary(*span, "demanded again here");
+            for (name, span) in path[..path.len() - 1].iter() {
+                let label = format!("`{}` demanded", name);
+                diag = diag.with_secondary(*span, label);
+            }
+            let path: Vec<_> = path.iter()
+                .map(|(name, _)| format!("`{}`", name))
+                .collect();
+            diag.with_note(format!("cycle: {}", path.join(" -> ")))
+        }
+        EvalError::DuplicateKey { key, span } => {
+            Diagnostic::error(format!("key `{}` is duplicated", key))
+                .with_primary(span, "duplicated here")
+        }
+        EvalError::Operator { msg, span } => {
+            Diagnostic::error(msg).with_primary(span, "applied here")
+        }
+        EvalError::TooDeep { depth, span } => {
+            Diagnostic::error("evaluation nested too deeply")
+                .with_primary(span, format!("nested over {} deep here", depth))
+                .with_note("most likely, a recursion doesn't end")
+        }
+        EvalError::Many(errs) => {
+            let mut errs = EvalError::Many(errs).flatten();
+            let more = errs.len() - 1;
+            from_eval_error(errs.remove(0))
+                .with_note(format!("and {} more errors", more))
+        }
+    }
+}
@@ -0,0 +1,12 @@
+//! Reporting errors of importing documents.
+
+use super::Diagnostic;
+use crate::import::ImportError;
+
+pub fn from_import_error(err: ImportError) -> Diagnostic {
+    let diag = Diagnostic::error(format!("invalid {}", err.format));
+    match err.span {
+        Some(span) => diag.with_primary(span, err.msg),
+        None => diag.with_note(err.msg),
+    }
+}
@@ -0,0 +1,12 @@
+//! Diagnostics reported to the user, rendered as labelled source snippets.
+
+pub mod report;
+pub mod parse;
+pub mod resolve;
+pub mod depend;
+pub mod visible;
+pub mod eval;
+pub mod emit;
+pub mod import;
+
+pub use report::{Diagnostic, Label, Level};
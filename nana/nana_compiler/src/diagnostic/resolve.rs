@@ -0,0 +1,70 @@
+//! Reporting errors of the resolution.
+
+use super::Diagnostic;
+use crate::resolve::resolve::{BlockKind, ResolveError};
+
+pub fn from_resolve_error(err: ResolveError) -> Diagnostic {
+    match err {
+        ResolveError::Unbound { name, span } => {
+            Diagnostic::error(format!("cannot find `{}` in this scope", name))
+                .with_primary(span, "not found")
+        }
+        ResolveError::Overlap { name, span, sibling } => {
+            Diagnostic::error(format!(
+                "`{}` is bound by a sibling in a tuple block", name
+            ))
+                .with_primary(span, "referred to here")
+                .with_secondary(sibling, "bound here")
+                .with_note(
+                    "bindings in `()` can't refer to each other; \
+                    use `[]` to bind sequentially or `{}` by dependency"
+                )
+        }
+        ResolveError::Early { name, span, later, captured } => {
+            let msg = if captured {
+                format!("`{}` is captured before it's bound", name)
+            } else {
+                format!("`{}` is referred to before it's bound", name)
+            };
+            Diagnostic::error(msg)
+                .with_primary(span, "referred to here")
+                .with_secondary(later, "bound later here")
+                .with_note(
+                    "bindings in `[]` are evaluated in order, \
+                    each seeing only the former; \
+                    use `{}` to bind by dependency"
+                )
+        }
+        ResolveError::Erroneous { span } => {
+            Diagnostic::error("erroneous expression")
+                .with_primary(span, "recovered from a syntax error")
+        }
+        ResolveError::Rebound { name, span, prev, block } => {
+            let (msg, note) = match block {
+                Some(BlockKind::Tuple) => (
+                    "a tuple block",
+                    Some("bindings in `()` can't shadow each other; \
+                        use `[]` to allow shadowing"),
+                ),
+                Some(BlockKind::Set) => (
+                    "a set block",
+                    Some("bindings in `{}` can't collide"),
+                ),
+                Some(BlockKind::Map) => (
+                    "a map block",
+                    Some("bindings in `{}` can't collide"),
+                ),
+                Some(BlockKind::List) | None => ("the same pattern", None),
+            };
+            let diag = Diagnostic::error(
+                format!("`{}` is bound more than once in {}", name, msg)
+            )
+                .with_primary(span, "bound again here")
+                .with_secondary(prev, "first bound here");
+            match note {
+                Some(note) => diag.with_note(note),
+                None => diag,
+            }
+        }
+    }
+}
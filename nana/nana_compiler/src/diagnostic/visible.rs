@@ -0,0 +1,17 @@
+//! Reporting errors of the visibility check.
+
+use super::Diagnostic;
+use crate::visible::visible::VisibleError;
+
+pub fn from_visible_error(err: VisibleError) -> Diagnostic {
+    match err {
+        VisibleError::Closed { name, span, def } => {
+            Diagnostic::error(format!("binder `{}` is not exposed", name))
+                .with_primary(span, "reached from outside of its block")
+                .with_secondary(def, "bound closed by `=` here")
+                .with_note(format!(
+                    "bind it by `{} := ..` to expose it", name
+                ))
+        }
+    }
+}
@@ -0,0 +1,233 @@
+//! Emitting values as CSS.
+//!
+//! The value is a map of selectors to rules, each a map of properties to
+//! values. A map in a rule is a nested rule, whose selector is joined to
+//! those of the rule: in place of `&` if any, e.g. `"&:hover"`, and as a
+//! descendant otherwise. Keys of at-rules, e.g. `"@media (..)"`, wrap the
+//! rules in their maps, or are statements if not maps, e.g. `"@import"`.
+//! Statements are out of rules, before those beside them.
+//!
+//! A value is a number or a string as it is, or a raw as its text; a pair
+//! of a number and a unit is a dimension, e.g. `(16, "px")` for `16px`.
+//! Tuples are the values in turn separated by spaces, as in `1px solid`,
+//! and lists by commas, as in `Helvetica, sans-serif`.
+
+use super::{EmitError, Path};
+use crate::eval::Value;
+use std::fmt::Write;
+
+/// Units of dimensions, after which a number is written without a space.
+const UNITS: &[&str] = &[
+    "%", "cm", "mm", "q", "in", "pc", "pt", "px", "em", "ex", "ch", "rem",
+    "lh", "rlh", "vw", "vh", "vmin", "vmax", "vb", "vi", "svw", "svh", "lvw",
+    "lvh", "dvw", "dvh", "cqw", "cqh", "deg", "grad", "rad", "turn", "s",
+    "ms", "hz", "khz", "dpi", "dpcm", "dppx", "x", "fr",
+];
+
+#[derive(Clone, Debug)]
+pub struct Css {
+    /// Whether indented, one declaration a line.
+    pub pretty: bool,
+}
+
+impl Default for Css {
+    fn default() -> Self {
+        Self { pretty: true }
+    }
+}
+
+pub fn css(value: &Value, config: &Css) -> Result<String, EmitError> {
+    let mut emitter = Emitter {
+        config,
+        out: String::new(),
+        path: Path::new(value),
+        depth: 0,
+    };
+    match value.peel() {
+        Value::Map(kvs) => emitter.rules(&[], kvs)?,
+        Value::Set(vs) if vs.is_empty() => (),
+        v => {
+            let (kind, span) = (v.kind(), value.span());
+            return Err(EmitError::Top { target: "css", kind, span })
+        }
+    }
+    let len = emitter.out.trim_end().len();
+    emitter.out.truncate(len);
+    Ok(emitter.out)
+}
+
+struct Emitter<'a> {
+    config: &'a Css,
+    out: String,
+    path: Path,
+    /// Depth of the at-rules the rules are in.
+    depth: usize,
+}
+
+impl Emitter<'_> {
+    /// Emits the statements, then the rule of the selectors, then the
+    /// rules nested in it.
+    fn rules(
+        &mut self, selectors: &[String], kvs: &[(Value, Value)]
+    ) -> Result<(), EmitError> {
+        let mut stmts = Vec::new();
+        let mut decls = Vec::new();
+        let mut nested = Vec::new();
+        for (k, v) in kvs.iter() {
+            self.path.key(k, v);
+            let key = match k.peel() {
+                Value::Str(key) => key.as_str(),
+                k => {
+                    return Err(EmitError::Key {
+                        target: "css",
+                        kind: k.kind(),
+                        path: self.path.to_string(),
+                        span: self.path.span(),
+                    })
+                }
+            };
+            match v.peel() {
+                Value::Map(kvs) => nested.push((k, v, key, &kvs[..])),
+                Value::Set(vs) if vs.is_empty() => (),
+                _ if key.starts_with('@') => {
+                    if !selectors.is_empty() {
+                        return Err(self.unsupported("at-statement in a rule"))
+                    }
+                    stmts.push((key, self.value(v)?));
+                }
+                _ if selectors.is_empty() => {
+                    return Err(self.unsupported("declaration out of rules"))
+                }
+                _ => decls.push((key, self.value(v)?)),
+            }
+            self.path.pop();
+        }
+        for (key, value) in stmts {
+            self.line();
+            let _ = write!(self.out, "{} {};", key, value);
+            self.end();
+        }
+        if !decls.is_empty() {
+            let prelude = selectors.join(&self.sep(","));
+            self.open(&prelude);
+            for (prop, value) in decls {
+                self.line();
+                let _ = write!(self.out, "{}:{}{};", prop, self.sep(""), value);
+                self.end();
+            }
+            self.close();
+        }
+        for (k, v, key, kvs) in nested {
+            self.path.key(k, v);
+            if key.starts_with('@') {
+                self.open(key);
+                self.rules(selectors, kvs)?;
+                self.close();
+            } else {
+                self.rules(&join(selectors, key), kvs)?;
+            }
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    fn value(&mut self, value: &Value) -> Result<String, EmitError> {
+        match value.peel() {
+            Value::Int(i) => Ok(i.to_string()),
+            Value::Float(x) if x.is_finite() => Ok(x.to_string()),
+            Value::Str(s) => Ok(s.clone()),
+            Value::Raw(r) => Ok(r.text.clone()),
+            Value::Tuple(vs) => match (vs[0].peel(), vs[1..].as_ref()) {
+                (Value::Int(_) | Value::Float(_), [unit])
+                if matches!(unit.peel(), Value::Str(u) if is_unit(u)) => {
+                    Ok(format!("{}{}", self.value(&vs[0])?, self.value(unit)?))
+                }
+                _ => self.values(vs, " "),
+            },
+            Value::List(vs) => {
+                let sep = self.sep(",");
+                self.values(vs, &sep)
+            }
+            v => Err(self.unsupported(v.kind())),
+        }
+    }
+
+    fn values(
+        &mut self, vs: &[Value], sep: &str
+    ) -> Result<String, EmitError> {
+        let mut out = Vec::new();
+        for (i, v) in vs.iter().enumerate() {
+            self.path.index(i, v);
+            out.push(self.value(v)?);
+            self.path.pop();
+        }
+        Ok(out.join(sep))
+    }
+
+    /// Opens a block, after a blank line if pretty.
+    fn open(&mut self, prelude: &str) {
+        if self.config.pretty && !self.out.is_empty() && self.depth == 0 {
+            self.out += "\n";
+        }
+        self.line();
+        let space = if self.config.pretty { " " } else { "" };
+        let _ = write!(self.out, "{}{}{{", prelude, space);
+        self.end();
+        self.depth += 1;
+    }
+
+    fn close(&mut self) {
+        self.depth -= 1;
+        self.line();
+        self.out += "}";
+        self.end();
+    }
+
+    fn line(&mut self) {
+        if self.config.pretty {
+            self.out += &"  ".repeat(self.depth);
+        }
+    }
+
+    fn end(&mut self) {
+        if self.config.pretty {
+            self.out += "\n";
+        }
+    }
+
+    /// The separator, followed by a space if pretty.
+    fn sep(&self, sep: &str) -> String {
+        if self.config.pretty {
+            format!("{} ", sep)
+        } else {
+            sep.to_owned()
+        }
+    }
+
+    fn unsupported(&self, kind: &'static str) -> EmitError {
+        let (path, span) = (self.path.to_string(), self.path.span());
+        EmitError::Unsupported { target: "css", kind, path, span }
+    }
+}
+
+fn is_unit(s: &str) -> bool {
+    UNITS.contains(&s.to_ascii_lowercase().as_str())
+}
+
+/// The selectors of a rule nested in the rule of the parents.
+fn join(parents: &[String], key: &str) -> Vec<String> {
+    let mut selectors = Vec::new();
+    for part in key.split(',').map(str::trim) {
+        if parents.is_empty() {
+            selectors.push(part.replace('&', ""));
+        }
+        for parent in parents.iter() {
+            selectors.push(if part.contains('&') {
+                part.replace('&', parent)
+            } else {
+                format!("{} {}", parent, part)
+            });
+        }
+    }
+    selectors
+}
@@ -0,0 +1,322 @@
+//! Emitting values as HTML.
+//!
+//! An element is a map of a `"tag"`, with optional `"attrs"`, a map of
+//! attributes, and `"children"`, the nodes within, but no other keys.
+//! Tuples, lists and sets are the nodes in turn, and `()` is none.
+//! Strings and numbers are text, escaped, while raws are written as they
+//! are, e.g. `[|<br>|]`.
+//!
+//! Attributes are strings or numbers; a list is joined by spaces, as
+//! classes are, and `()` stands for a boolean attribute, e.g. `disabled`.
+//! Raws are written as they are in attributes too, except for `"`, which
+//! is escaped as `&quot;` lest it end the attribute.
+
+use super::{EmitError, Path};
+use crate::eval::Value;
+
+/// Elements that have no children nor end tag.
+const VOID: &[&str] = &[
+    "area", "base", "br", "col", "embed", "hr", "img", "input", "link",
+    "meta", "source", "track", "wbr",
+];
+
+#[derive(Clone, Debug)]
+pub struct Html {
+    /// Whether `<!DOCTYPE html>` is written before an `html` element.
+    pub doctype: bool,
+}
+
+impl Default for Html {
+    fn default() -> Self {
+        Self { doctype: true }
+    }
+}
+
+pub fn html(value: &Value, config: &Html) -> Result<String, EmitError> {
+    let mut emitter = Emitter {
+        out: String::new(),
+        path: Path::new(value),
+    };
+    let top = element(value).and_then(|kvs| field(kvs, "tag"));
+    let top = top.map(Value::peel);
+    if config.doctype && matches!(top, Some(Value::Str(s)) if s == "html") {
+        emitter.out += "<!DOCTYPE html>\n";
+    }
+    emitter.node(value)?;
+    Ok(emitter.out)
+}
+
+struct Emitter {
+    out: String,
+    path: Path,
+}
+
+impl Emitter {
+    fn node(&mut self, value: &Value) -> Result<(), EmitError> {
+        match value.peel() {
+            Value::Unit => (),
+            Value::Int(i) => self.out += &i.to_string(),
+            Value::Float(x) => self.out += &format!("{:?}", x),
+            Value::Str(s) => self.out += &escape(s),
+            Value::Raw(r) => self.out += &r.text,
+            Value::Tuple(vs) | Value::List(vs) => self.nodes(vs)?,
+            Value::Set(vs) => self.nodes(vs)?,
+            Value::Map(kvs) if kvs.is_empty() => (),
+            Value::Map(kvs) => self.element(kvs)?,
+            Value::Closure(_) => return Err(self.unsupported("closure")),
+            Value::Block(_) => unreachable!("peeled"),
+        }
+        Ok(())
+    }
+
+    fn nodes<'v, I>(&mut self, vs: I) -> Result<(), EmitError>
+    where I: IntoIterator<Item = &'v Value> {
+        for (i, v) in vs.into_iter().enumerate() {
+            self.path.index(i, v);
+            self.node(v)?;
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    fn element(&mut self, kvs: &[(Value, Value)]) -> Result<(), EmitError> {
+        let name = match field(kvs, "tag").map(Value::peel) {
+            Some(Value::Str(name)) => name.clone(),
+            _ => return Err(self.unsupported("map without a tag")),
+        };
+        if !valid(&name) {
+            return Err(self.name(&name))
+        }
+        let unknown = kvs.iter().find(|(k, _)| !matches!(k.peel(),
+            Value::Str(k) if ["tag", "attrs", "children"].contains(&k.as_str())
+        ));
+        if let Some((k, v)) = unknown {
+            self.path.key(k, v);
+            return Err(EmitError::Key {
+                target: "html",
+                kind: k.kind(),
+                path: self.path.to_string(),
+                span: self.path.span(),
+            })
+        }
+        self.out += "<";
+        self.out += &name;
+        if let Some((k, attrs)) = entry(kvs, "attrs") {
+            self.path.key(k, attrs);
+            self.attrs(attrs)?;
+            self.path.pop();
+        }
+        self.out += ">";
+        let children = entry(kvs, "children");
+        if VOID.contains(&name.as_str()) {
+            return match children.map(|(_, v)| v.peel()) {
+                None | Some(Value::Unit) => Ok(()),
+                Some(Value::List(vs)) if vs.is_empty() => Ok(()),
+                Some(_) => Err(self.unsupported("children of a void element")),
+            }
+        }
+        if let Some((k, children)) = children {
+            self.path.key(k, children);
+            self.node(children)?;
+            self.path.pop();
+        }
+        self.out += "</";
+        self.out += &name;
+        self.out += ">";
+        Ok(())
+    }
+
+    fn attrs(&mut self, attrs: &Value) -> Result<(), EmitError> {
+        let kvs = match attrs.peel() {
+            Value::Map(kvs) => kvs,
+            Value::Set(vs) if vs.is_empty() => return Ok(()),
+            _ => return Err(self.unsupported("attributes but a map")),
+        };
+        for (k, v) in kvs.iter() {
+            self.path.key(k, v);
+            let name = match k.peel() {
+                Value::Str(name) if valid(name) => name,
+                Value::Str(name) => return Err(self.name(name)),
+                k => {
+                    return Err(EmitError::Key {
+                        target: "html",
+                        kind: k.kind(),
+                        path: self.path.to_string(),
+                        span: self.path.span(),
+                    })
+                }
+            };
+            self.out += " ";
+            self.out += name;
+            if let Value::Unit = v.peel() {
+                self.path.pop();
+                continue
+            }
+            let words: Vec<_> = match v.peel() {
+                Value::Tuple(vs) | Value::List(vs) => vs.iter().collect(),
+                Value::Set(vs) => vs.iter().collect(),
+                v => vec![v],
+            };
+            let text = words.into_iter()
+                .map(|v| self.text(v))
+                .collect::<Result<Vec<_>, _>>()?
+                .join(" ");
+            self.out += "=\"";
+            self.out += &text;
+            self.out += "\"";
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    /// The value of an attribute, escaped; only `"` is, if raw.
+    fn text(&self, value: &Value) -> Result<String, EmitError> {
+        match value.peel() {
+            Value::Int(i) => Ok(i.to_string()),
+            Value::Float(x) => Ok(format!("{:?}", x)),
+            Value::Str(s) => Ok(escape(s)),
+            Value::Raw(r) => Ok(r.text.replace('"', "&quot;")),
+            v => Err(self.unsupported(v.kind())),
+        }
+    }
+
+    fn unsupported(&self, kind: &'static str) -> EmitError {
+        let (path, span) = (self.path.to_string(), self.path.span());
+        EmitError::Unsupported { target: "html", kind, path, span }
+    }
+
+    fn name(&self, name: &str) -> EmitError {
+        let name = name.to_owned();
+        let (path, span) = (self.path.to_string(), self.path.span());
+        EmitError::Name { target: "html", name, path, span }
+    }
+}
+
+/// The entries of an element.
+fn element(value: &Value) -> Option<&[(Value, Value)]> {
+    match value.peel() {
+        Value::Map(kvs) => Some(kvs),
+        _ => None,
+    }
+}
+
+fn entry<'v>(
+    kvs: &'v [(Value, Value)], key: &str
+) -> Option<&'v (Value, Value)> {
+    kvs.iter().find(|(k, _)| matches!(k.peel(), Value::Str(s) if s == key))
+}
+
+fn field<'v>(kvs: &'v [(Value, Value)], key: &str) -> Option<&'v Value> {
+    entry(kvs, key).map(|(_, v)| v)
+}
+
+/// Whether the name of a tag or an attribute is written as it is.
+fn valid(name: &str) -> bool {
+    name.chars().next().is_some_and(|c| c.is_ascii_alphabetic())
+        && name.chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_:".contains(c))
+}
+
+fn escape(s: &str) -> String {
+    let mut out = String::new();
+    for c in s.chars() {
+        match c {
+            '&' => out += "&amp;",
+            '<' => out += "&lt;",
+            '>' => out += "&gt;",
+            '"' => out += "&quot;",
+            '\'' => out += "&#39;",
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit::tests::emit;
+
+    #[test]
+    fn elements() {
+        let text = r#"{
+            el tag attrs children :=
+                { "tag": tag, "attrs": attrs, "children": children };
+            row name up := el "tr" {} [
+                el "td" {} name,
+                el "td"
+                    { "class": ["status", (? up | 1 -> "up" | _ -> "down")] }
+                    (? up | 1 -> "ok" | _ -> "<down>"),
+            ];
+            "tag": "html",
+            "children": [
+                el "head" {} [
+                    { "tag": "meta", "attrs": { "charset": "utf-8" } },
+                ],
+                el "body" {} [
+                    el "table" { "hidden": () } [row "db" 1, row "web" 0],
+                    [|<br>|],
+                    "a & b",
+                ],
+            ],
+        }"#;
+        let config = Html::default();
+        assert_eq!(emit(text, html, &config), "<!DOCTYPE html>\n<html>\
+            <head><meta charset=\"utf-8\"></head>\
+            <body><table hidden>\
+            <tr><td>db</td><td class=\"status up\">ok</td></tr>\
+            <tr><td>web</td><td class=\"status down\">&lt;down&gt;</td></tr>\
+            </table><br>a &amp; b</body></html>");
+    }
+
+    #[test]
+    fn raws() {
+        let text = r#"{
+            "tag": "a",
+            "attrs": { "href": [|/?q=a&b|], "title": [|say "&lt;hi&gt;"|] },
+            "children": [|<b>"hi"</b>|],
+        }"#;
+        assert_eq!(emit(text, html, &Html::default()), "\
+            <a href=\"/?q=a&b\" title=\"say &quot;&lt;hi&gt;&quot;\">\
+            <b>\"hi\"</b></a>");
+    }
+
+    #[test]
+    fn rejections() {
+        let config = Html::default();
+        assert_eq!(emit(r#"{ "tag": "p x" }"#, html, &config), r#"
+error: `p x` is not a valid name in html
+ --> main.na:1:1
+  |
+1 | { "tag": "p x" }
+  | ^^^^^^^^^^^^^^^^ evaluated from here
+  = note: found at the top
+"#[1..]);
+        let text = r#"{ "tag": "br", "children": ["x"] }"#;
+        assert_eq!(emit(text, html, &config), r#"
+error: children of a void element has no html
+ --> main.na:1:1
+  |
+1 | { "tag": "br", "children": ["x"] }
+  | ^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^^ evaluated from here
+  = note: found at the top
+"#[1..]);
+        assert_eq!(emit(r#"[{ "class": "x" }]"#, html, &config), r#"
+error: map without a tag has no html
+ --> main.na:1:2
+  |
+1 | [{ "class": "x" }]
+  |  ^^^^^^^^^^^^^^^^ evaluated from here
+  = note: found at `[0]`
+"#[1..]);
+        let text = r#"{ "tag": "div", "childs": ["x"] }"#;
+        assert_eq!(emit(text, html, &config), r#"
+error: string can't be a key in html
+ --> main.na:1:27
+  |
+1 | { "tag": "div", "childs": ["x"] }
+  |                           ^^^^^ evaluated from here
+  = note: found at `.childs`
+"#[1..]);
+    }
+}
@@ -0,0 +1,225 @@
+//! Emitting values as JSON.
+//!
+//! Maps are objects, keyed by strings or ints, in the order written.
+//! Tuples, lists and sets are arrays, sets in their order. Raws are strings
+//! of their text. Closures have no JSON.
+
+use super::{Empty, EmitError, Path};
+use crate::eval::Value;
+use std::fmt::Write;
+
+#[derive(Clone, Debug)]
+pub struct Json {
+    /// Whether indented, one entry a line.
+    pub pretty: bool,
+    /// What `()` is emitted as.
+    pub unit: Empty,
+    /// What `[]` is emitted as.
+    pub nil: Empty,
+    /// What `{}` is emitted as.
+    pub null: Empty,
+}
+
+impl Default for Json {
+    fn default() -> Self {
+        Self {
+            pretty: true,
+            unit: Empty::Null,
+            nil: Empty::Array,
+            null: Empty::Object,
+        }
+    }
+}
+
+pub fn json(value: &Value, config: &Json) -> Result<String, EmitError> {
+    let mut emitter = Emitter {
+        config,
+        out: String::new(),
+        path: Path::new(value),
+        depth: 0,
+    };
+    emitter.value(value)?;
+    Ok(emitter.out)
+}
+
+struct Emitter<'a> {
+    config: &'a Json,
+    out: String,
+    path: Path,
+    depth: usize,
+}
+
+impl Emitter<'_> {
+    fn value(&mut self, value: &Value) -> Result<(), EmitError> {
+        match value.peel() {
+            Value::Unit => self.empty(self.config.unit),
+            Value::List(vs) if vs.is_empty() => self.empty(self.config.nil),
+            Value::Set(vs) if vs.is_empty() => self.empty(self.config.null),
+            Value::Map(kvs) if kvs.is_empty() => {
+                self.empty(self.config.null)
+            }
+            Value::Int(i) => {
+                let _ = write!(self.out, "{}", i);
+            }
+            Value::Float(x) if x.is_finite() => {
+                let _ = write!(self.out, "{:?}", x);
+            }
+            Value::Float(_) => return Err(self.unsupported("infinite float")),
+            Value::Str(s) => self.out += &quote(s),
+            Value::Raw(r) => self.out += &quote(&r.text),
+            Value::Tuple(vs) | Value::List(vs) => self.array(vs)?,
+            Value::Set(vs) => self.array(vs)?,
+            Value::Map(kvs) => self.object(kvs)?,
+            Value::Closure(_) => return Err(self.unsupported("closure")),
+            Value::Block(_) => unreachable!("peeled"),
+        }
+        Ok(())
+    }
+
+    fn empty(&mut self, empty: Empty) {
+        self.out += match empty {
+            Empty::Null => "null",
+            Empty::Array => "[]",
+            Empty::Object => "{}",
+        };
+    }
+
+    fn array<'v, I>(&mut self, vs: I) -> Result<(), EmitError>
+    where I: IntoIterator<Item = &'v Value> {
+        self.out += "[";
+        self.depth += 1;
+        for (i, v) in vs.into_iter().enumerate() {
+            if i > 0 {
+                self.out += ",";
+            }
+            self.newline();
+            self.path.index(i, v);
+            self.value(v)?;
+            self.path.pop();
+        }
+        self.depth -= 1;
+        self.newline();
+        self.out += "]";
+        Ok(())
+    }
+
+    fn object(&mut self, kvs: &[(Value, Value)]) -> Result<(), EmitError> {
+        self.out += "{";
+        self.depth += 1;
+        for (i, (k, v)) in kvs.iter().enumerate() {
+            if i > 0 {
+                self.out += ",";
+            }
+            self.newline();
+            self.path.key(k, v);
+            match k.peel() {
+                Value::Str(s) => self.out += &quote(s),
+                Value::Int(i) => self.out += &quote(&i.to_string()),
+                k => {
+                    let (target, kind) = ("json", k.kind());
+                    let path = self.path.to_string();
+                    let span = self.path.span();
+                    return Err(EmitError::Key { target, kind, path, span })
+                }
+            }
+            self.out += if self.config.pretty { ": " } else { ":" };
+            self.value(v)?;
+            self.path.pop();
+        }
+        self.depth -= 1;
+        self.newline();
+        self.out += "}";
+        Ok(())
+    }
+
+    fn newline(&mut self) {
+        if self.config.pretty {
+            self.out += "\n";
+            self.out += &"    ".repeat(self.depth);
+        }
+    }
+
+    fn unsupported(&self, kind: &'static str) -> EmitError {
+        let (path, span) = (self.path.to_string(), self.path.span());
+        EmitError::Unsupported { target: "json", kind, path, span }
+    }
+}
+
+/// The string quoted and escaped.
+pub(crate) fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{:04x}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit::tests::emit;
+
+    #[test]
+    fn pretty() {
+        let text = r#"{
+            name = "Alice";
+            name: { "scores": [90, 85.5], "tags": { "b", "a" } },
+            "quote": "say \"hi\"\n",
+        }"#;
+        assert_eq!(emit(text, json, &Json::default()), r#"{
+    "Alice": {
+        "scores": [
+            90,
+            85.5
+        ],
+        "tags": [
+            "a",
+            "b"
+        ]
+    },
+    "quote": "say \"hi\"\n"
+}"#);
+    }
+
+    #[test]
+    fn compact() {
+        let config = Json { pretty: false, ..Json::default() };
+        assert_eq!(emit("((), [], {}, (1, [|x|]))", json, &config),
+            r#"[null,[],{},[1,"x"]]"#);
+        let config = Json {
+            pretty: false,
+            unit: Empty::Object,
+            nil: Empty::Null,
+            null: Empty::Null,
+        };
+        assert_eq!(emit("((), [], {}, { 1: 2 })", json, &config),
+            r#"[{},null,null,{"1":2}]"#);
+        assert_eq!(emit("{ \"f\": [(+)] }", json, &config), "\
+error: closure has no json
+ --> main.na:1:8
+  |
+1 | { \"f\": [(+)] }
+  |        ^^^^^ evaluated from here
+  = note: found at `.f[0]`
+");
+        assert_eq!(emit("{ (1, 2): 3 }", json, &config), "\
+error: tuple can't be a key in json
+ --> main.na:1:1
+  |
+1 | { (1, 2): 3 }
+  | ^^^^^^^^^^^^^ evaluated from here
+  = note: found at `[(1, 2)]`
+");
+    }
+}
@@ -0,0 +1,155 @@
+//! Emitters of evaluated values into other data notations.
+
+pub mod css;
+pub mod html;
+pub mod json;
+pub mod toml;
+pub mod yaml;
+
+pub use css::{css, Css};
+pub use html::{html, Html};
+pub use json::{json, Json};
+pub use toml::{toml, Toml};
+pub use yaml::{yaml, Yaml};
+
+use crate::base::Span;
+use crate::eval::Value;
+use std::str::FromStr;
+
+#[derive(Clone, Debug)]
+pub enum EmitError {
+    /// A value the notation has nothing for.
+    Unsupported {
+        target: &'static str,
+        kind: &'static str,
+        /// Where it's in the value emitted.
+        path: String,
+        /// The innermost block it's evaluated in, if known.
+        span: Option<Span>,
+    },
+    /// A key of a map the notation can't key by.
+    Key {
+        target: &'static str,
+        kind: &'static str,
+        path: String,
+        span: Option<Span>,
+    },
+    /// A name of a tag or the like, which would be read otherwise.
+    Name {
+        target: &'static str,
+        name: String,
+        path: String,
+        span: Option<Span>,
+    },
+    /// An array of values of different kinds, in strict TOML.
+    Mixed {
+        kinds: (&'static str, &'static str),
+        path: String,
+        span: Option<Span>,
+    },
+    /// A value that can't be a document.
+    Top {
+        target: &'static str,
+        kind: &'static str,
+        span: Option<Span>,
+    },
+}
+
+/// What an empty block is emitted as: `()`, `[]` or `{}`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Empty {
+    Null,
+    Array,
+    Object,
+}
+
+impl FromStr for Empty {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "null" => Ok(Empty::Null),
+            "array" => Ok(Empty::Array),
+            "object" => Ok(Empty::Object),
+            _ => Err(format!(
+                "expected `null`, `array` or `object`, found `{}`", s
+            )),
+        }
+    }
+}
+
+/// Where a value is within the value emitted, e.g. `.alice.exam[0]`,
+/// along with the spans of the blocks evaluated to the values on the way.
+#[derive(Clone)]
+pub(crate) struct Path {
+    steps: Vec<String>,
+    spans: Vec<Option<Span>>,
+}
+
+impl Path {
+    pub fn new(top: &Value) -> Self {
+        Self { steps: Vec::new(), spans: vec![top.span()] }
+    }
+
+    pub fn index(&mut self, i: usize, value: &Value) {
+        self.steps.push(format!("[{}]", i));
+        self.spans.push(value.span());
+    }
+
+    pub fn key(&mut self, key: &Value, value: &Value) {
+        self.steps.push(match key.peel() {
+            Value::Str(s) if is_name(s) => format!(".{}", s),
+            key => format!("[{}]", key),
+        });
+        self.spans.push(value.span());
+    }
+
+    pub fn pop(&mut self) {
+        self.steps.pop();
+        self.spans.pop();
+    }
+
+    /// The innermost span known on the way.
+    pub fn span(&self) -> Option<Span> {
+        self.spans.iter().rev().find_map(|&span| span)
+    }
+}
+
+impl std::fmt::Display for Path {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.steps.is_empty() {
+            write!(f, "the top")
+        } else {
+            write!(f, "`{}`", self.steps.concat())
+        }
+    }
+}
+
+/// Whether the string is written bare in a path.
+fn is_name(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::base::SrcFile;
+    use crate::diagnostic::emit::from_emit_error;
+    use crate::eval::eval;
+    use crate::external::parse;
+    use crate::Flatten;
+
+    type Emitter<C> = fn(&Value, &C) -> Result<String, EmitError>;
+
+    /// Emits the value of the source, or renders the error.
+    pub fn emit<C>(text: &str, emitter: Emitter<C>, config: &C) -> String {
+        let src = SrcFile::new("main.na", text);
+        let (nana, diags) = parse(&src);
+        assert!(diags.is_empty(), "{:?}", diags);
+        let value = eval(nana.unwrap().flatten()).unwrap();
+        match emitter(&value, config) {
+            Ok(out) => out,
+            Err(err) => from_emit_error(err).render(&src),
+        }
+    }
+}
@@ -0,0 +1,315 @@
+//! Emitting values as TOML.
+//!
+//! The value must be a map, which is the top table. A map in a table is an
+//! inline table if short enough, and a standard table otherwise; an array
+//! of maps is an array of tables. Keys are strings, and there is nothing
+//! for `()` or closures. In strict mode, arrays are of values of a kind,
+//! as required before TOML 1.0.
+
+use super::json::quote;
+use super::{EmitError, Path};
+use crate::eval::Value;
+use std::fmt::Write;
+
+#[derive(Clone, Debug)]
+pub struct Toml {
+    /// Whether arrays of values of different kinds are rejected.
+    pub strict: bool,
+    /// The longest a map is written inline, e.g. `{ a = 1, b = 2 }`.
+    pub inline: usize,
+}
+
+impl Default for Toml {
+    fn default() -> Self {
+        Self { strict: true, inline: 40 }
+    }
+}
+
+pub fn toml(value: &Value, config: &Toml) -> Result<String, EmitError> {
+    let mut emitter = Emitter {
+        config,
+        out: String::new(),
+        path: Path::new(value),
+    };
+    match value.peel() {
+        Value::Map(kvs) => emitter.table(&[], kvs)?,
+        Value::Set(vs) if vs.is_empty() => (),
+        v => {
+            let (kind, span) = (v.kind(), value.span());
+            return Err(EmitError::Top { target: "toml", kind, span })
+        }
+    }
+    let len = emitter.out.trim_end().len();
+    emitter.out.truncate(len);
+    Ok(emitter.out)
+}
+
+/// A table written after the key-values of its parent.
+enum Section<'v> {
+    Table(&'v [(Value, Value)]),
+    Array(Vec<&'v [(Value, Value)]>),
+}
+
+struct Emitter<'a> {
+    config: &'a Toml,
+    out: String,
+    path: Path,
+}
+
+impl Emitter<'_> {
+    /// Emits the entries of the table named by the keys, key-values first.
+    fn table(
+        &mut self, keys: &[String], kvs: &[(Value, Value)]
+    ) -> Result<(), EmitError> {
+        let mut sections = Vec::new();
+        for (k, v) in kvs.iter() {
+            self.path.key(k, v);
+            let key = self.key(k)?;
+            match self.section(v) {
+                Some(section) => sections.push((key, k, v, section)),
+                None => {
+                    let v = self.inline(v)?;
+                    let _ = writeln!(self.out, "{} = {}", key, v);
+                }
+            }
+            self.path.pop();
+        }
+        for (key, k, v, section) in sections {
+            self.path.key(k, v);
+            let mut keys = keys.to_vec();
+            keys.push(key);
+            match section {
+                Section::Table(kvs) => {
+                    self.header("[", &keys, "]");
+                    self.table(&keys, kvs)?;
+                }
+                Section::Array(tables) => {
+                    let items = array(v).expect("an array");
+                    for (i, kvs) in tables.into_iter().enumerate() {
+                        self.path.index(i, items[i]);
+                        self.header("[[", &keys, "]]");
+                        self.table(&keys, kvs)?;
+                        self.path.pop();
+                    }
+                }
+            }
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    fn header(&mut self, open: &str, keys: &[String], close: &str) {
+        if !self.out.is_empty() {
+            self.out += "\n";
+        }
+        let _ = writeln!(self.out, "{}{}{}", open, keys.join("."), close);
+    }
+
+    /// How the value is written as a table, unless inline.
+    fn section<'v>(&mut self, value: &'v Value) -> Option<Section<'v>> {
+        match value.peel() {
+            Value::Map(kvs) if !kvs.is_empty() => {
+                let path = self.path.clone();
+                let inline = self.inline(value);
+                self.path = path;
+                match inline {
+                    Ok(s) if s.len() <= self.config.inline => None,
+                    _ => Some(Section::Table(kvs)),
+                }
+            }
+            _ => {
+                let tables = array(value)?.into_iter()
+                    .map(|v| match v.peel() {
+                        Value::Map(kvs) if !kvs.is_empty() => Some(&kvs[..]),
+                        _ => None,
+                    })
+                    .collect::<Option<Vec<_>>>()?;
+                (!tables.is_empty()).then_some(Section::Array(tables))
+            }
+        }
+    }
+
+    fn key(&self, key: &Value) -> Result<String, EmitError> {
+        match key.peel() {
+            Value::Str(s) if bare(s) => Ok(s.clone()),
+            Value::Str(s) => Ok(quote(s)),
+            k => Err(EmitError::Key {
+                target: "toml",
+                kind: k.kind(),
+                path: self.path.to_string(),
+                span: self.path.span(),
+            }),
+        }
+    }
+
+    /// The value in a line.
+    fn inline(&mut self, value: &Value) -> Result<String, EmitError> {
+        let mut out = String::new();
+        match value.peel() {
+            Value::Int(i) => out = i.to_string(),
+            Value::Float(x) if x.is_nan() => out += "nan",
+            Value::Float(x) if x.is_infinite() => {
+                out += if *x > 0.0 { "inf" } else { "-inf" }
+            }
+            Value::Float(x) => out = format!("{:?}", x),
+            Value::Str(s) => out = quote(s),
+            Value::Raw(r) => out = quote(&r.text),
+            Value::Set(vs) if vs.is_empty() => out += "{}",
+            Value::Map(kvs) if kvs.is_empty() => out += "{}",
+            Value::Map(kvs) => {
+                out += "{ ";
+                for (i, (k, v)) in kvs.iter().enumerate() {
+                    if i > 0 {
+                        out += ", ";
+                    }
+                    self.path.key(k, v);
+                    let (k, v) = (self.key(k)?, self.inline(v)?);
+                    let _ = write!(out, "{} = {}", k, v);
+                    self.path.pop();
+                }
+                out += " }";
+            }
+            Value::Unit => return Err(self.unsupported("unit")),
+            Value::Closure(_) => return Err(self.unsupported("closure")),
+            _ => {
+                let items = array(value).expect("an array");
+                if self.config.strict {
+                    self.homogeneous(&items)?;
+                }
+                out += "[";
+                for (i, v) in items.into_iter().enumerate() {
+                    if i > 0 {
+                        out += ", ";
+                    }
+                    self.path.index(i, v);
+                    out += &self.inline(v)?;
+                    self.path.pop();
+                }
+                out += "]";
+            }
+        }
+        Ok(out)
+    }
+
+    fn homogeneous(&self, items: &[&Value]) -> Result<(), EmitError> {
+        let kind = |v: &Value| match v.peel() {
+            Value::Tuple(_) | Value::List(_) | Value::Set(_) => "array",
+            Value::Raw(_) => "string",
+            v => v.kind(),
+        };
+        let first = match items.first() {
+            Some(v) => kind(v),
+            None => return Ok(()),
+        };
+        match items.iter().map(|v| kind(v)).find(|k| *k != first) {
+            Some(other) => Err(EmitError::Mixed {
+                kinds: (first, other),
+                path: self.path.to_string(),
+                span: self.path.span(),
+            }),
+            None => Ok(()),
+        }
+    }
+
+    fn unsupported(&self, kind: &'static str) -> EmitError {
+        let (path, span) = (self.path.to_string(), self.path.span());
+        EmitError::Unsupported { target: "toml", kind, path, span }
+    }
+}
+
+/// The items of a tuple, list or set.
+fn array(value: &Value) -> Option<Vec<&Value>> {
+    match value.peel() {
+        Value::Tuple(vs) | Value::List(vs) => Some(vs.iter().collect()),
+        Value::Set(vs) => Some(vs.iter().collect()),
+        _ => None,
+    }
+}
+
+/// Whether the key is written bare.
+fn bare(s: &str) -> bool {
+    !s.is_empty()
+        && s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit::tests::emit;
+
+    #[test]
+    fn tables() {
+        let text = r#"{
+            dep version = { "version": version };
+            "package": {
+                "name": "nana_compiler",
+                "version": "0.1.0",
+                "edition": "2018",
+            },
+            "dependencies": {
+                "anyhow": dep "1",
+                "lalrpop-util": { "version": "0.19", "features": ["lexer"] },
+            },
+            "bin": [{ "name": "nanac", "path": "src/bin/nanac.rs" }],
+            "workspace": { "members": [] },
+        }"#;
+        let config = Toml::default();
+        assert_eq!(emit(text, toml, &config), r#"workspace = { members = [] }
+
+[package]
+name = "nana_compiler"
+version = "0.1.0"
+edition = "2018"
+
+[dependencies]
+anyhow = { version = "1" }
+
+[dependencies.lalrpop-util]
+version = "0.19"
+features = ["lexer"]
+
+[[bin]]
+name = "nanac"
+path = "src/bin/nanac.rs""#);
+    }
+
+    #[test]
+    fn rejections() {
+        let text = "{\n    \"a\": [1, \"2\"],\n}";
+        let config = Toml { strict: false, ..Toml::default() };
+        assert_eq!(emit(text, toml, &config), "a = [1, \"2\"]");
+        assert_eq!(emit(text, toml, &Toml::default()), "\
+error: array of int and string
+ --> main.na:2:10
+  |
+2 |     \"a\": [1, \"2\"],
+  |          ^^^^^^^^ evaluated from here
+  = note: arrays in strict toml are of values of a kind
+  = note: found at `.a`
+");
+        let text = "{\n    \"a\": { \"b\": [()] },\n}";
+        assert_eq!(emit(text, toml, &Toml::default()), "\
+error: unit has no toml
+ --> main.na:2:18
+  |
+2 |     \"a\": { \"b\": [()] },
+  |                  ^^ evaluated from here
+  = note: found at `.a.b[0]`
+");
+        assert_eq!(emit("{ 1: 2 }", toml, &Toml::default()), "\
+error: int can't be a key in toml
+ --> main.na:1:1
+  |
+1 | { 1: 2 }
+  | ^^^^^^^^ evaluated from here
+  = note: found at `[1]`
+");
+        assert_eq!(emit("[1]", toml, &Toml::default()), "\
+error: list can't be a document of toml
+ --> main.na:1:1
+  |
+1 | [1]
+  | ^^^ evaluated from here
+");
+    }
+}
@@ -0,0 +1,298 @@
+//! Emitting values as YAML, in block style.
+//!
+//! Values are mapped as in JSON, see `json`, except that floats may be
+//! infinite. A compound value bound to a binder and found in several places
+//! is emitted once, anchored by the name of the binder, and aliased in the
+//! others.
+
+use super::json::quote;
+use super::{Empty, EmitError, Path};
+use crate::eval::value::{Block, Value};
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+use std::sync::Arc;
+
+#[derive(Clone, Debug)]
+pub struct Yaml {
+    /// Whether values found in several places are anchored and aliased,
+    /// instead of repeated.
+    pub anchors: bool,
+    /// What `()` is emitted as.
+    pub unit: Empty,
+    /// What `[]` is emitted as.
+    pub nil: Empty,
+    /// What `{}` is emitted as.
+    pub null: Empty,
+}
+
+impl Default for Yaml {
+    fn default() -> Self {
+        Self {
+            anchors: true,
+            unit: Empty::Null,
+            nil: Empty::Array,
+            null: Empty::Object,
+        }
+    }
+}
+
+pub fn yaml(value: &Value, config: &Yaml) -> Result<String, EmitError> {
+    let mut emitter = Emitter {
+        config,
+        out: String::new(),
+        path: Path::new(value),
+        counts: HashMap::new(),
+        anchors: HashMap::new(),
+        names: HashSet::new(),
+    };
+    if config.anchors {
+        emitter.count(value);
+    }
+    match emitter.scalar(value)? {
+        Some(s) => emitter.out = s,
+        None => emitter.block(value.peel(), 0, false)?,
+    }
+    let len = emitter.out.trim_end().len();
+    emitter.out.truncate(len);
+    Ok(emitter.out)
+}
+
+/// How a node is emitted with regard to anchors.
+enum Anchor {
+    None,
+    First(String),
+    Alias(String),
+}
+
+struct Emitter<'a> {
+    config: &'a Yaml,
+    out: String,
+    path: Path,
+    /// Places each named value is found in, counted up to twice.
+    counts: HashMap<*const Block, usize>,
+    /// Anchors of the named values emitted so far.
+    anchors: HashMap<*const Block, String>,
+    names: HashSet<String>,
+}
+
+impl Emitter<'_> {
+    fn count(&mut self, value: &Value) {
+        if let (Value::Block(b), Some(_)) = (value, value.name()) {
+            let count = self.counts.entry(Arc::as_ptr(b)).or_insert(0);
+            *count += 1;
+            if *count > 1 {
+                return
+            }
+        }
+        match value.peel() {
+            Value::Tuple(vs) | Value::List(vs) => {
+                vs.iter().for_each(|v| self.count(v))
+            }
+            Value::Set(vs) => vs.iter().for_each(|v| self.count(v)),
+            Value::Map(kvs) => kvs.iter().for_each(|(_, v)| self.count(v)),
+            _ => (),
+        }
+    }
+
+    fn anchor(&mut self, value: &Value) -> Anchor {
+        let (b, name) = match (value, value.name()) {
+            (Value::Block(b), Some(name)) => (Arc::as_ptr(b), name),
+            _ => return Anchor::None,
+        };
+        if let Some(anchor) = self.anchors.get(&b) {
+            return Anchor::Alias(anchor.clone())
+        }
+        if self.counts.get(&b).copied().unwrap_or(0) < 2 {
+            return Anchor::None
+        }
+        let name: String = name.chars()
+            .map(|c| if c.is_alphanumeric() || c == '-' { c } else { '_' })
+            .collect();
+        let mut anchor = name.clone();
+        for i in 2.. {
+            if self.names.insert(anchor.clone()) {
+                break
+            }
+            anchor = format!("{}_{}", name, i);
+        }
+        self.anchors.insert(b, anchor.clone());
+        Anchor::First(anchor)
+    }
+
+    /// The value in a line, unless a non-empty collection.
+    fn scalar(&self, value: &Value) -> Result<Option<String>, EmitError> {
+        let empty = |e| match e {
+            Empty::Null => "null".to_owned(),
+            Empty::Array => "[]".to_owned(),
+            Empty::Object => "{}".to_owned(),
+        };
+        Ok(Some(match value.peel() {
+            Value::Unit => empty(self.config.unit),
+            Value::List(vs) if vs.is_empty() => empty(self.config.nil),
+            Value::Set(vs) if vs.is_empty() => empty(self.config.null),
+            Value::Map(kvs) if kvs.is_empty() => empty(self.config.null),
+            Value::Int(i) => i.to_string(),
+            Value::Float(x) if x.is_nan() => ".nan".to_owned(),
+            Value::Float(x) if x.is_infinite() => {
+                if *x > 0.0 { ".inf" } else { "-.inf" }.to_owned()
+            }
+            Value::Float(x) => format!("{:?}", x),
+            Value::Str(s) => string(s),
+            Value::Raw(r) => string(&r.text),
+            Value::Closure(_) => {
+                let (target, kind) = ("yaml", "closure");
+                let (path, span) = (self.path.to_string(), self.path.span());
+                return Err(EmitError::Unsupported { target, kind, path, span })
+            }
+            _ => return Ok(None),
+        }))
+    }
+
+    /// Emits the entries of a non-empty collection a line each, indented;
+    /// the first is already indented if `inline`.
+    fn block(
+        &mut self, value: &Value, indent: usize, inline: bool
+    ) -> Result<(), EmitError> {
+        let items: Vec<_> = match value {
+            Value::Tuple(vs) | Value::List(vs) => vs.iter().collect(),
+            Value::Set(vs) => vs.iter().collect(),
+            Value::Map(kvs) => {
+                for (i, (k, v)) in kvs.iter().enumerate() {
+                    if i > 0 || !inline {
+                        self.out += &" ".repeat(indent);
+                    }
+                    self.path.key(k, v);
+                    let key = match k.peel() {
+                        Value::Map(_) | Value::Set(_) | Value::List(_)
+                        | Value::Tuple(_) | Value::Unit => None,
+                        k => self.scalar(k)?,
+                    };
+                    let key = key.ok_or_else(|| EmitError::Key {
+                        target: "yaml",
+                        kind: k.kind(),
+                        path: self.path.to_string(),
+                        span: self.path.span(),
+                    })?;
+                    self.out += &key;
+                    self.out += ":";
+                    self.node(v, indent, false)?;
+                    self.path.pop();
+                }
+                return Ok(())
+            }
+            _ => unreachable!("a collection"),
+        };
+        for (i, v) in items.into_iter().enumerate() {
+            if i > 0 || !inline {
+                self.out += &" ".repeat(indent);
+            }
+            self.out += "-";
+            self.path.index(i, v);
+            self.node(v, indent, true)?;
+            self.path.pop();
+        }
+        Ok(())
+    }
+
+    /// Emits the value after a `-` or a `key:` at the indent.
+    fn node(
+        &mut self, value: &Value, indent: usize, item: bool
+    ) -> Result<(), EmitError> {
+        let anchored = match self.anchor(value) {
+            Anchor::None => false,
+            Anchor::First(anchor) => {
+                let _ = write!(self.out, " &{}", anchor);
+                true
+            }
+            Anchor::Alias(alias) => {
+                let _ = writeln!(self.out, " *{}", alias);
+                return Ok(())
+            }
+        };
+        if let Some(s) = self.scalar(value)? {
+            let _ = writeln!(self.out, " {}", s);
+        } else if item && !anchored {
+            self.out += " ";
+            self.block(value.peel(), indent + 2, true)?;
+        } else {
+            self.out += "\n";
+            self.block(value.peel(), indent + 2, false)?;
+        }
+        Ok(())
+    }
+}
+
+/// The string, quoted unless plain enough to be read as a string.
+fn string(s: &str) -> String {
+    let plain = s.chars().next()
+        .is_some_and(|c| c.is_alphabetic() || c == '_')
+        && s.chars().all(|c| c.is_alphanumeric() || "_-./ ".contains(c))
+        && !s.ends_with(' ')
+        && !matches!(
+            s.to_lowercase().as_str(),
+            "true" | "false" | "yes" | "no" | "on" | "off" | "null" | "y" | "n"
+        );
+    if plain {
+        s.to_owned()
+    } else {
+        quote(s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::emit::tests::emit;
+
+    #[test]
+    fn blocks() {
+        let text = r#"{
+            "name": "Alice", "tags": { "on", "b c" },
+            "points": [{ "x": 1, "y": 2.5 }, [], ()],
+            "note": "a: b\n",
+        }"#;
+        assert_eq!(emit(text, yaml, &Yaml::default()), r#"name: Alice
+tags:
+  - b c
+  - "on"
+points:
+  - x: 1
+    "y": 2.5
+  - []
+  - null
+note: "a: b\n""#);
+    }
+
+    #[test]
+    fn anchors() {
+        let text = r#"{
+            base = { "host": "localhost", "ports": [80, 443] };
+            base' = base;
+            once = [1];
+            "dev": base, "prod": [base', once],
+        }"#;
+        assert_eq!(emit(text, yaml, &Yaml::default()), r#"dev: &base
+  host: localhost
+  ports:
+    - 80
+    - 443
+prod:
+  - *base
+  - - 1"#);
+        let config = Yaml { anchors: false, ..Yaml::default() };
+        assert!(!emit(text, yaml, &config).contains('&'));
+    }
+
+    #[test]
+    fn rejections() {
+        let text = "{ \"a\": [1, (+)] }";
+        assert_eq!(emit(text, yaml, &Yaml::default()), "\
+error: closure has no yaml
+ --> main.na:1:8
+  |
+1 | { \"a\": [1, (+)] }
+  |        ^^^^^^^^ evaluated from here
+  = note: found at `.a[1]`
+");
+    }
+}
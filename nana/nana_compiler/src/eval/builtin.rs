@@ -0,0 +1,134 @@
+//! Builtin operators. Comparisons and logics take and give ints,
+//! `1` for truth and `0` for falsity.
+
+use super::value::Value;
+use num_bigint::BigInt;
+use num_traits::{One, Signed, ToPrimitive, Zero};
+
+/// The most bits of an int given by powers, about a megabit; larger powers
+/// would take long to compute.
+const MAX_BITS: u64 = 1 << 20;
+
+/// Number of arguments the operator takes.
+pub fn arity(op: &str) -> usize {
+    match op {
+        "!" => 1,
+        _ => 2,
+    }
+}
+
+/// Applies the operator on all its arguments, except `$` which is left to
+/// the evaluator.
+pub fn apply(op: &str, args: &[Value]) -> Result<Value, String> {
+    let args: Vec<_> = args.iter().map(Value::peel).collect();
+    match (op, &args[..]) {
+        ("!", [v]) => Ok(Value::bool(!truth(v)?)),
+        ("&&", [a, b]) => Ok(Value::bool(truth(a)? && truth(b)?)),
+        ("==", [a, b]) => Ok(Value::bool(a.compare(b).is_eq())),
+        ("!=", [a, b]) => Ok(Value::bool(a.compare(b).is_ne())),
+        ("<" | ">" | "<=" | ">=", [a, b]) => {
+            if !comparable(a, b) {
+                return Err(mismatch(op, a, b))
+            }
+            let ord = a.compare(b);
+            Ok(Value::bool(match op {
+                "<" => ord.is_lt(),
+                ">" => ord.is_gt(),
+                "<=" => ord.is_le(),
+                _ => ord.is_ge(),
+            }))
+        }
+        ("++", [Value::Str(a), Value::Str(b)]) => {
+            Ok(Value::Str(format!("{}{}", a, b)))
+        }
+        ("++", [Value::List(a), Value::List(b)]) => {
+            Ok(Value::List(a.iter().chain(b).cloned().collect()))
+        }
+        ("+" | "-" | "*" | "/" | "%" | "**" | "^", [a, b]) => {
+            arith(op, a, b)
+        }
+        (_, [a, b]) => Err(mismatch(op, a, b)),
+        _ => Err(format!("`{}` is not supported", op)),
+    }
+}
+
+fn truth(v: &Value) -> Result<bool, String> {
+    match v {
+        Value::Int(i) => Ok(!i.is_zero()),
+        v => Err(format!("expected an int as truth, found {}", v.kind())),
+    }
+}
+
+fn comparable(a: &Value, b: &Value) -> bool {
+    let number = |v: &Value| matches!(v, Value::Int(_) | Value::Float(_));
+    a.kind() == b.kind() || number(a) && number(b)
+}
+
+fn mismatch(op: &str, a: &Value, b: &Value) -> String {
+    format!("`{}` can't be applied to {} and {}", op, a.kind(), b.kind())
+}
+
+fn arith(op: &str, a: &Value, b: &Value) -> Result<Value, String> {
+    match (a, b) {
+        (Value::Int(a), Value::Int(b)) => int(op, a, b),
+        (Value::Int(a), Value::Float(b)) => Ok(float(op, to_f64(a), *b)),
+        (Value::Float(a), Value::Int(b)) => Ok(float(op, *a, to_f64(b))),
+        (Value::Float(a), Value::Float(b)) => Ok(float(op, *a, *b)),
+        (a, b) => Err(mismatch(op, a, b)),
+    }
+}
+
+fn to_f64(i: &BigInt) -> f64 {
+    i.to_f64().unwrap_or(f64::NAN)
+}
+
+fn int(op: &str, a: &BigInt, b: &BigInt) -> Result<Value, String> {
+    let i = match op {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" | "%" if b.is_zero() => {
+            return Err("division by zero".to_owned())
+        }
+        "/" => a / b,
+        "%" => a % b,
+        _ => return pow(op, a, b),
+    };
+    Ok(Value::Int(i))
+}
+
+fn pow(op: &str, a: &BigInt, b: &BigInt) -> Result<Value, String> {
+    if b.is_negative() {
+        // a negative power is fractional
+        return Ok(Value::Float(to_f64(a).powf(to_f64(b))))
+    }
+    if a.magnitude().is_one() || a.is_zero() {
+        // powers of 0, 1 and -1 stay small
+        let i = match (b.is_zero(), a.is_negative() && !b.bit(0)) {
+            (true, _) => BigInt::one(),
+            (_, true) => -a,
+            _ => a.clone(),
+        };
+        return Ok(Value::Int(i))
+    }
+    // the power has at least this many bits
+    let least = b.to_u64()
+        .map_or(u64::MAX, |b| (a.bits() - 1).saturating_mul(b));
+    match b.to_u32() {
+        Some(b) if least < MAX_BITS => Ok(Value::Int(a.pow(b))),
+        _ => Err(format!(
+            "`{}` would give an int of over {} bits", op, MAX_BITS
+        )),
+    }
+}
+
+fn float(op: &str, a: f64, b: f64) -> Value {
+    Value::Float(match op {
+        "+" => a + b,
+        "-" => a - b,
+        "*" => a * b,
+        "/" => a / b,
+        "%" => a % b,
+        _ => a.powf(b),
+    })
+}
@@ -0,0 +1,109 @@
+//! Environments of evaluation, where bindings are thunks evaluated on
+//! demand.
+//!
+//! All binders bound by a binding share its thunk: demanding any of them
+//! evaluates the binding once, and fills all of their slots. A thunk being
+//! evaluated is black-holed, so that demanding it again reports a cycle
+//! instead of looping; demanding it from another thread waits for it.
+
+use super::eval::EvalError;
+use super::value::Value;
+use crate::resolve::ast::{DefId, GatedBlock};
+use std::collections::HashMap;
+use std::sync::{Arc, Condvar, Mutex, OnceLock};
+
+/// Frames of slots, the innermost first; shared by the closures and thunks
+/// evaluated within.
+#[derive(Clone, Default)]
+pub struct Env(Option<Arc<Frame>>);
+
+struct Frame {
+    slots: HashMap<DefId, Arc<Slot>>,
+    parent: Env,
+}
+
+/// Where a binder's value is kept once evaluated.
+pub struct Slot {
+    pub(super) value: OnceLock<Value>,
+    /// The binding to evaluate for the value, if not bound in place.
+    pub(super) thunk: Option<Arc<Thunk>>,
+    /// Whether bound by `<..>`, thus standing for any name exposed.
+    pub(super) open: bool,
+}
+
+/// The `index`th binding of the block of `owner`.
+pub(super) struct Thunk {
+    pub owner: Arc<GatedBlock>,
+    pub index: usize,
+    pub state: Mutex<State>,
+    /// Notified once evaluated, to the tasks waiting for it.
+    pub done: Condvar,
+}
+
+pub(super) enum State {
+    /// Not yet demanded, to be evaluated in the env.
+    Pending(Env),
+    /// Being evaluated by the task, i.e. black-holed.
+    Forcing(usize),
+    Done,
+    Failed(EvalError),
+}
+
+impl Env {
+    /// A new env with the slots innermost.
+    pub fn push(&self, slots: HashMap<DefId, Arc<Slot>>) -> Env {
+        Env(Some(Arc::new(Frame { slots, parent: self.clone() })))
+    }
+
+    pub fn get(&self, def: DefId) -> Option<&Arc<Slot>> {
+        let mut env = self;
+        while let Some(frame) = &env.0 {
+            if let Some(slot) = frame.slots.get(&def) {
+                return Some(slot)
+            }
+            env = &frame.parent;
+        }
+        None
+    }
+
+    /// The part of the env captured by a gated block, on the outermost
+    /// frame of builtins.
+    pub fn capture(&self, g: &GatedBlock) -> Env {
+        let slots = g.captures.iter().filter_map(|c| {
+            Some((c.def, self.get(c.def)?.clone()))
+        }).collect();
+        let mut root = self;
+        while let Some(frame) = &root.0 {
+            if frame.parent.0.is_none() {
+                break
+            }
+            root = &frame.parent;
+        }
+        root.push(slots)
+    }
+}
+
+impl Slot {
+    /// A slot bound in place.
+    pub fn new(value: Value, open: bool) -> Self {
+        let slot = Self { value: OnceLock::new(), thunk: None, open };
+        let _ = slot.value.set(value);
+        slot
+    }
+
+    pub(super) fn lazy(thunk: Arc<Thunk>, open: bool) -> Self {
+        Self { value: OnceLock::new(), thunk: Some(thunk), open }
+    }
+}
+
+impl Thunk {
+    /// A thunk pending in an empty env, until `pend`.
+    pub fn new(owner: Arc<GatedBlock>, index: usize) -> Self {
+        let state = Mutex::new(State::Pending(Env::default()));
+        Self { owner, index, state, done: Condvar::new() }
+    }
+
+    pub fn pend(&self, env: Env) {
+        *self.state.lock().expect("poisoned") = State::Pending(env);
+    }
+}
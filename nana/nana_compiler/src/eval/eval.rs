@@ -0,0 +1,799 @@
+//! Evaluates nana by walking the resolved ast.
+//!
+//! Bindings are evaluated on demand, when referred to, projected or
+//! exposed, so that those never demanded are never evaluated, whatever
+//! the block; see `env`. Values of a block are evaluated along with the
+//! block. A block is valued as `Value::Block`, keeping its span and the
+//! bindings it exposes.
+//!
+//! Values of a `()` block are evaluated in parallel, as many at a time as
+//! threads are left in the pool; they're in the order written all the
+//! same, and the errors of all of them are reported.
+//!
+//! Exprs are evaluated nested at most `MAX_DEPTH` deep, on threads of
+//! `STACK` bytes of stack, so that deep recursion fails with an error
+//! rather than overflowing the stack.
+
+use super::builtin;
+use super::env::{Env, Slot, State, Thunk};
+use super::pool::Pool;
+use super::value::{self, Closure, Value};
+use crate::diagnostic::{eval::from_eval_error, Diagnostic};
+use crate::flatten::ast as fa;
+use crate::resolve::{ast::*, resolve};
+use crate::visible::visible;
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// The most exprs evaluated nested in one another.
+const MAX_DEPTH: usize = 20_000;
+/// The stack of a thread evaluating, enough for `MAX_DEPTH`.
+const STACK: usize = 256 << 20;
+
+#[derive(Clone, Debug)]
+pub enum EvalError {
+    NotFunction {
+        kind: &'static str,
+        span: Span,
+    },
+    /// No pattern matches the value.
+    NoMatch {
+        kind: &'static str,
+        span: Span,
+    },
+    NotExposed {
+        name: String,
+        span: Span,
+    },
+    /// A binding demanded while being evaluated, by the binders demanded
+    /// from the first demand on, each with where it's demanded.
+    Cycle(Vec<(String, Span)>),
+    DuplicateKey {
+        key: String,
+        span: Span,
+    },
+    Operator {
+        msg: String,
+        span: Span,
+    },
+    /// Exprs nested over `MAX_DEPTH` deep, by recursion most likely.
+    TooDeep {
+        depth: usize,
+        span: Span,
+    },
+    /// Errors of several values of a `()` block, in the order written.
+    Many(Vec<EvalError>),
+}
+
+impl EvalError {
+    /// The errors in the order written, without `Many`.
+    pub fn flatten(self) -> Vec<EvalError> {
+        match self {
+            EvalError::Many(errs) => {
+                errs.into_iter().flat_map(EvalError::flatten).collect()
+            }
+            err => vec![err],
+        }
+    }
+}
+
+/// How to evaluate.
+#[derive(Clone, Debug)]
+pub struct Config {
+    /// Threads running at most at a time; all cores by default.
+    pub threads: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        let threads = std::thread::available_parallelism()
+            .map_or(1, usize::from);
+        Self { threads }
+    }
+}
+
+type Result<T> = std::result::Result<T, EvalError>;
+
+/// Resolves and checks the flattened ast, and evaluates it.
+pub fn eval(nana: fa::Nana) -> std::result::Result<Value, Vec<Diagnostic>> {
+    eval_with(nana, &Config::default())
+}
+
+pub fn eval_with(
+    nana: fa::Nana, config: &Config
+) -> std::result::Result<Value, Vec<Diagnostic>> {
+    let nana = resolve(nana)?;
+    visible(&nana)?;
+    let builtins = nana.builtins.iter()
+        .map(|&def| {
+            let op = nana.defs[def.0].inner_ref().as_str().to_owned();
+            let args = Vec::new();
+            let value = Value::Closure(Arc::new(Closure::Builtin { op, args }));
+            (def, Arc::new(Slot::new(value, false)))
+        })
+        .collect();
+    let env = Env::default().push(builtins);
+    let body = Arc::new(nana.body.inner());
+    let pool = Arc::new(Pool::new(config.threads));
+    let res = thread::scope(|s| {
+        thread::Builder::new()
+            .stack_size(STACK)
+            .spawn_scoped(s, || Evaluator::new(pool).block(&body, &env))
+            .expect("spawned")
+            .join()
+            .unwrap_or_else(|panic| std::panic::resume_unwind(panic))
+    });
+    res.map_err(|err| {
+        err.flatten().into_iter().map(from_eval_error).collect()
+    })
+}
+
+/// Evaluates on a thread, as a task of the pool.
+struct Evaluator {
+    pool: Arc<Pool>,
+    task: usize,
+    /// The thunks being evaluated, each with the binder demanding it,
+    /// including those of the tasks this task works for.
+    forcing: RefCell<Vec<(Arc<Thunk>, String, Span)>>,
+    /// The exprs being evaluated, including those of the tasks this task
+    /// works for.
+    depth: Cell<usize>,
+}
+
+impl Evaluator {
+    fn new(pool: Arc<Pool>) -> Self {
+        let task = pool.task();
+        let (forcing, depth) = Default::default();
+        Self { pool, task, forcing, depth }
+    }
+
+    /// An evaluator to work for this one on another thread.
+    fn fork(&self) -> Self {
+        let fork = Self::new(self.pool.clone());
+        fork.forcing.borrow_mut().extend(self.forcing.borrow().iter().cloned());
+        fork.depth.set(self.depth.get());
+        fork
+    }
+
+    /// A gated block is a closure unless it has no trace.
+    fn gate(&self, g: &Arc<GatedBlock>, env: &Env) -> Result<Value> {
+        if g.traces.is_empty() {
+            return self.block(g, env)
+        }
+        Ok(Value::Closure(Arc::new(Closure::Gated {
+            gate: g.clone(),
+            env: env.capture(g),
+            args: Vec::new(),
+        })))
+    }
+
+    /// Evaluates the block of the gated block, its traces already bound.
+    fn block(&self, owner: &Arc<GatedBlock>, env: &Env) -> Result<Value> {
+        let block = owner.block.inner_ref();
+        let (bds, vls, pairs) = block.spaces();
+        let mut slots = HashMap::new();
+        let mut thunks = Vec::new();
+        for (i, abs) in bds.iter().enumerate() {
+            let thunk = Arc::new(Thunk::new(owner.clone(), i));
+            visit_defs(&abs.inner_ref().trace, &mut |def| {
+                let open = def.binder.as_str() == "..";
+                slots.insert(def.id, Arc::new(Slot::lazy(thunk.clone(), open)));
+            });
+            thunks.push(thunk);
+        }
+        let env = env.push(slots);
+        for thunk in thunks {
+            thunk.pend(env.clone());
+        }
+        let value = match block {
+            Block::Tuple(_, _) => {
+                let mut values = self.parallel(vls, &env)?;
+                match values.len() {
+                    0 => Value::Unit,
+                    1 => values.pop().expect("one value"),
+                    _ => Value::Tuple(values),
+                }
+            }
+            Block::List(_, _) => Value::List(self.sequential(vls, &env)?),
+            Block::Set(_, _) => {
+                let values = self.sequential(vls, &env)?;
+                Value::Set(values.into_iter().collect::<BTreeSet<_>>())
+            }
+            Block::Map(_, _) => {
+                let mut kvs: Vec<(Value, Value)> = Vec::new();
+                for pair in pairs.iter() {
+                    let pair = pair.inner_ref();
+                    let key = self.expr(&pair.key, &env)?;
+                    if kvs.iter().any(|(k, _)| *k == key) {
+                        let key = key.to_string();
+                        let span = pair.key.span();
+                        return Err(EvalError::DuplicateKey { key, span })
+                    }
+                    kvs.push((key, self.expr(&pair.val, &env)?));
+                }
+                Value::Map(kvs)
+            }
+        };
+        let mut space = BTreeMap::new();
+        for abs in bds.iter().filter(|abs| abs.inner_ref().exposed) {
+            self.expose(&abs.inner_ref().trace, &env, &mut space)?;
+        }
+        // `( a )` is merely `a`
+        if space.is_empty() && matches!(block, Block::Tuple(_, _))
+            && vls.len() == 1 {
+            return Ok(value)
+        }
+        let span = Some(owner.block.span());
+        let name = Default::default();
+        Ok(Value::Block(Arc::new(value::Block { space, value, span, name })))
+    }
+
+    fn sequential(
+        &self, vls: &[Spanned<Expr>], env: &Env
+    ) -> Result<Vec<Value>> {
+        vls.iter().map(|e| self.expr(e, env)).collect()
+    }
+
+    /// Evaluates the values on workers of the pool if any left, and
+    /// otherwise in turn, reporting the errors of all of them.
+    fn parallel(
+        &self, vls: &[Spanned<Expr>], env: &Env
+    ) -> Result<Vec<Value>> {
+        let workers = self.pool.reserve(vls.len());
+        let results = if workers == 0 {
+            vls.iter().map(|e| self.expr(e, env)).collect()
+        } else {
+            let forks: Vec<_> = (0..workers).map(|_| self.fork()).collect();
+            self.pool.join(self.task, forks.iter().map(|f| f.task).collect());
+            let next = AtomicUsize::new(0);
+            let slots: Vec<_> = vls.iter().map(|_| Mutex::new(None)).collect();
+            let (next, slots) = (&next, &slots);
+            std::thread::scope(|s| {
+                for fork in forks {
+                    let worker = thread::Builder::new().stack_size(STACK);
+                    worker.spawn_scoped(s, move || loop {
+                        let i = next.fetch_add(1, Ordering::Relaxed);
+                        let e = match vls.get(i) {
+                            Some(e) => e,
+                            None => break,
+                        };
+                        let res = fork.expr(e, env);
+                        *slots[i].lock().expect("poisoned") = Some(res);
+                    }).expect("spawned");
+                }
+            });
+            self.pool.resume(self.task);
+            self.pool.release(workers);
+            slots.iter().map(|slot| {
+                slot.lock().expect("poisoned").take().expect("evaluated")
+            }).collect::<Vec<_>>()
+        };
+        let mut values = Vec::new();
+        let mut errs = Vec::new();
+        for res in results {
+            match res {
+                Ok(value) => values.push(value),
+                Err(err) => errs.push(err),
+            }
+        }
+        match errs.len() {
+            0 => Ok(values),
+            1 => Err(errs.pop().expect("one error")),
+            _ => Err(EvalError::Many(errs)),
+        }
+    }
+
+    /// Adds the slots of an exposed pattern to the space; `<..>` exposes
+    /// whatever exposed by its value, which is thus evaluated.
+    fn expose(
+        &self,
+        pat: &Spanned<Pattern>,
+        env: &Env,
+        space: &mut BTreeMap<String, Arc<Slot>>,
+    ) -> Result<()> {
+        let mut slots = Vec::new();
+        visit_defs(pat, &mut |def| {
+            let slot = env.get(def.id).expect("bound by the block");
+            slots.push((def.binder.as_str().to_owned(), slot.clone()));
+        });
+        for (name, slot) in slots {
+            if !slot.open {
+                space.insert(name, slot);
+                continue
+            }
+            let span = pat.span();
+            if let Value::Block(b) = self.force(&slot, &name, span)? {
+                space.extend(b.space.clone());
+            }
+        }
+        Ok(())
+    }
+
+    /// The value of the slot, evaluating its binding if not yet, or
+    /// waiting for it if evaluated by another task.
+    fn force(&self, slot: &Slot, name: &str, span: Span) -> Result<Value> {
+        if let Some(value) = slot.value.get() {
+            return Ok(value.clone())
+        }
+        let thunk = slot.thunk.as_ref().expect("bound in place");
+        let mut state = thunk.state.lock().expect("poisoned");
+        loop {
+            match &*state {
+                State::Pending(_) => break,
+                &State::Forcing(task) => {
+                    if task == self.task || !self.pool.block(self.task, task) {
+                        return Err(self.cycle(thunk, name, span))
+                    }
+                    state = thunk.done.wait(state).expect("poisoned");
+                    self.pool.resume(self.task);
+                }
+                State::Done => {
+                    return Ok(slot.value.get().expect("evaluated").clone())
+                }
+                State::Failed(err) => return Err(err.clone()),
+            }
+        }
+        let forcing = State::Forcing(self.task);
+        let env = match std::mem::replace(&mut *state, forcing) {
+            State::Pending(env) => env,
+            _ => unreachable!("pending"),
+        };
+        drop(state);
+        self.forcing.borrow_mut().push((thunk.clone(), name.to_owned(), span));
+        let res = self.bind(thunk, &env);
+        self.forcing.borrow_mut().pop();
+        *thunk.state.lock().expect("poisoned") = match &res {
+            Ok(()) => State::Done,
+            Err(err) => State::Failed(err.clone()),
+        };
+        thunk.done.notify_all();
+        res?;
+        Ok(slot.value.get().expect("evaluated").clone())
+    }
+
+    /// The cycle of binders demanded from the first demand of the thunk.
+    fn cycle(&self, thunk: &Arc<Thunk>, name: &str, span: Span) -> EvalError {
+        let forcing = self.forcing.borrow();
+        let start = forcing.iter()
+            .position(|(t, _, _)| Arc::ptr_eq(t, thunk))
+            .unwrap_or(0);
+        let mut path: Vec<_> = forcing[start..].iter()
+            .map(|(_, name, span)| (name.clone(), *span))
+            .collect();
+        path.push((name.to_owned(), span));
+        EvalError::Cycle(path)
+    }
+
+    /// Evaluates the binding of the thunk, filling the slots of its binders.
+    fn bind(&self, thunk: &Thunk, env: &Env) -> Result<()> {
+        let (bds, _, _) = thunk.owner.block.inner_ref().spaces();
+        let abs = bds[thunk.index].inner_ref();
+        let value = self.gate(abs.src.inner_ref(), env)?;
+        let mut bound = Vec::new();
+        if !self.pattern(&abs.trace, &value, env, &mut bound)? {
+            let kind = value.kind();
+            return Err(EvalError::NoMatch { kind, span: abs.trace.span() })
+        }
+        let mut names = HashMap::new();
+        visit_defs(&abs.trace, &mut |def| {
+            names.insert(def.id, def.binder.as_str().to_owned());
+        });
+        for (def, value) in bound {
+            let slot = env.get(def).expect("bound by the block");
+            let value = match names[&def].as_str() {
+                ".." => value,
+                name => value.named(name),
+            };
+            let _ = slot.value.set(value);
+        }
+        Ok(())
+    }
+
+    /// Matches the value against the pattern, collecting the values of
+    /// its binders into `bound`.
+    fn pattern(
+        &self,
+        pat: &Spanned<Pattern>,
+        value: &Value,
+        env: &Env,
+        bound: &mut Vec<(DefId, Value)>,
+    ) -> Result<bool> {
+        let peeled = value.peel();
+        match pat.inner_ref() {
+            Pattern::Alias(al, p) => {
+                let al = self.pattern(al, value, env, bound)?;
+                Ok(al && self.pattern(p, value, env, bound)?)
+            }
+            Pattern::Wild | Pattern::Forall => Ok(true),
+            Pattern::Literal(l) => Ok(literal(l).compare(peeled).is_eq()),
+            Pattern::Binder(def) => {
+                bound.push((def.id, value.clone()));
+                Ok(true)
+            }
+            Pattern::Exposure(ex) => {
+                for ex in ex.iter() {
+                    match ex.inner_ref() {
+                        ExposurePattern::Binder(def) => {
+                            let name = def.binder.as_str();
+                            let v = self.project(value, name, ex.span())?;
+                            bound.push((def.id, v));
+                        }
+                        ExposurePattern::All(def) => {
+                            bound.push((def.id, value.clone()));
+                        }
+                    }
+                }
+                Ok(true)
+            }
+            Pattern::Tuple(ps) => match peeled {
+                Value::Unit => self.sequence(ps, &[], env, bound),
+                Value::Tuple(vs) => self.sequence(ps, vs, env, bound),
+                _ => Ok(false),
+            },
+            Pattern::List(ps) => match peeled {
+                Value::List(vs) => self.sequence(ps, vs, env, bound),
+                _ => Ok(false),
+            },
+            Pattern::Map(ps) => {
+                let kvs = match peeled {
+                    Value::Map(kvs) => kvs,
+                    _ => return Ok(false),
+                };
+                for p in ps.iter() {
+                    let p = p.inner_ref();
+                    let key = self.expr(&p.key, env)?;
+                    let v = match kvs.iter().find(|(k, _)| *k == key) {
+                        Some((_, v)) => v,
+                        None => return Ok(false),
+                    };
+                    if !self.pattern(&p.val, v, env, bound)? {
+                        return Ok(false)
+                    }
+                }
+                Ok(true)
+            }
+        }
+    }
+
+    /// Matches the values against the patterns, where a `..` matches
+    /// any number of values.
+    fn sequence(
+        &self,
+        ps: &[Spanned<Pattern>],
+        vs: &[Value],
+        env: &Env,
+        bound: &mut Vec<(DefId, Value)>,
+    ) -> Result<bool> {
+        let rest = ps.iter()
+            .position(|p| matches!(p.inner_ref(), Pattern::Forall));
+        let (front, back) = match rest {
+            Some(i) if vs.len() + 1 >= ps.len() => (&ps[..i], &ps[i + 1..]),
+            None if vs.len() == ps.len() => (ps, &[][..]),
+            _ => return Ok(false),
+        };
+        let back_vs = &vs[vs.len() - back.len()..];
+        for (p, v) in front.iter().zip(vs).chain(back.iter().zip(back_vs)) {
+            if !self.pattern(p, v, env, bound)? {
+                return Ok(false)
+            }
+        }
+        Ok(true)
+    }
+
+    fn expr(&self, e: &Spanned<Expr>, env: &Env) -> Result<Value> {
+        let depth = self.depth.get();
+        if depth >= MAX_DEPTH {
+            return Err(EvalError::TooDeep { depth, span: e.span() })
+        }
+        self.depth.set(depth + 1);
+        let res = self.nested(e, env);
+        self.depth.set(depth);
+        res
+    }
+
+    fn nested(&self, e: &Spanned<Expr>, env: &Env) -> Result<Value> {
+        let span = e.span();
+        match e.inner_ref() {
+            Expr::Literal(l) => Ok(literal(l)),
+            Expr::Binder(u) => self.lookup(u, span, env),
+            Expr::GatedBlock(g) => self.gate(g, env),
+            Expr::Application(f, a) => {
+                let f = self.expr(f, env)?;
+                let a = self.expr(a, env)?;
+                self.apply(f, a, span)
+            }
+            Expr::Projection(blk, b) => {
+                let blk = self.expr(blk, env)?;
+                self.project(&blk, b.inner_ref().as_str(), b.span())
+            }
+            Expr::Matching(scrutinee, bs) => {
+                let value = self.expr(scrutinee, env)?;
+                for b in bs.iter() {
+                    let b = b.inner_ref();
+                    let mut bound = Vec::new();
+                    if self.pattern(&b.pattern, &value, env, &mut bound)? {
+                        let env = env.push(in_place(bound));
+                        return self.expr(&b.body, &env)
+                    }
+                }
+                let kind = value.kind();
+                Err(EvalError::NoMatch { kind, span: scrutinee.span() })
+            }
+        }
+    }
+
+    fn lookup(&self, u: &Use, span: Span, env: &Env) -> Result<Value> {
+        let name = u.binder.as_str();
+        let slot = env.get(u.def).expect("resolved");
+        let value = self.force(slot, name, span)?;
+        // found through `<..>`
+        if slot.open && name != ".." {
+            return self.project(&value, name, span)
+        }
+        Ok(value)
+    }
+
+    fn project(&self, value: &Value, name: &str, span: Span) -> Result<Value> {
+        if let Value::Block(b) = value {
+            if let Some(slot) = b.space.get(name) {
+                return self.force(slot, name, span)
+            }
+            // exposed in turn
+            if let Value::Block(_) = &b.value {
+                return self.project(&b.value, name, span)
+            }
+        }
+        let name = name.to_owned();
+        Err(EvalError::NotExposed { name, span })
+    }
+
+    fn apply(&self, f: Value, arg: Value, span: Span) -> Result<Value> {
+        let closure = match f.peel() {
+            Value::Closure(c) => c.clone(),
+            f => return Err(EvalError::NotFunction { kind: f.kind(), span }),
+        };
+        match &*closure {
+            Closure::Gated { gate, env, args } => {
+                let mut args = args.clone();
+                args.push(arg);
+                if args.len() < gate.traces.len() {
+                    let (gate, env) = (gate.clone(), env.clone());
+                    let closure = Closure::Gated { gate, env, args };
+                    return Ok(Value::Closure(Arc::new(closure)))
+                }
+                let traces = gate.traces.iter()
+                    .map(|trace| trace.inner_ref().id)
+                    .zip(args)
+                    .collect();
+                self.block(gate, &env.push(in_place(traces)))
+            }
+            Closure::Builtin { op, args } => {
+                let mut args = args.clone();
+                args.push(arg);
+                if args.len() < builtin::arity(op) {
+                    let op = op.clone();
+                    let closure = Closure::Builtin { op, args };
+                    return Ok(Value::Closure(Arc::new(closure)))
+                }
+                if op == "$" {
+                    let arg = args.pop().expect("two args");
+                    let f = args.pop().expect("two args");
+                    return self.apply(f, arg, span)
+                }
+                builtin::apply(op, &args)
+                    .map_err(|msg| EvalError::Operator { msg, span })
+            }
+        }
+    }
+}
+
+/// Slots of binders bound in place.
+fn in_place(bound: Vec<(DefId, Value)>) -> HashMap<DefId, Arc<Slot>> {
+    bound.into_iter()
+        .map(|(def, value)| (def, Arc::new(Slot::new(value, false))))
+        .collect()
+}
+
+fn literal(l: &Literal) -> Value {
+    match l {
+        Literal::Int(i) => Value::Int(i.clone()),
+        Literal::Float(x) => Value::Float(*x),
+        Literal::Str(s) => Value::Str(s.value.clone()),
+        Literal::Raw(r) => Value::Raw(r.clone()),
+    }
+}
+
+fn visit_defs(pat: &Spanned<Pattern>, f: &mut dyn FnMut(&Def)) {
+    match pat.inner_ref() {
+        Pattern::Alias(al, p) => {
+            visit_defs(al, f);
+            visit_defs(p, f);
+        }
+        Pattern::Binder(def) => f(def),
+        Pattern::Exposure(ex) => {
+            for ex in ex.iter() {
+                match ex.inner_ref() {
+                    ExposurePattern::Binder(def) => f(def),
+                    ExposurePattern::All(def) => f(def),
+                }
+            }
+        }
+        Pattern::Tuple(ps) | Pattern::List(ps) => {
+            for p in ps.iter() {
+                visit_defs(p, f);
+            }
+        }
+        Pattern::Map(ps) => {
+            for p in ps.iter() {
+                visit_defs(&p.inner_ref().val, f);
+            }
+        }
+        Pattern::Wild | Pattern::Forall | Pattern::Literal(_) => (),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external::parse;
+    use crate::Flatten;
+
+    fn run(text: &str) -> String {
+        run_with(text, 1).remove(0)
+    }
+
+    /// The value, or the messages of the errors.
+    fn run_with(text: &str, threads: usize) -> Vec<String> {
+        let src = SrcFile::new("main.na", text);
+        let (nana, diags) = parse(&src);
+        assert!(diags.is_empty());
+        match eval_with(nana.unwrap().flatten(), &Config { threads }) {
+            Ok(value) => vec![value.to_string()],
+            Err(diags) => diags.into_iter().map(|d| d.msg).collect(),
+        }
+    }
+
+    #[test]
+    fn blocks() {
+        assert_eq!(run("()"), "()");
+        assert_eq!(run("(binded = 0; 1)"), "1");
+        assert_eq!(run("[ one = 0; one = 1; final = one; final ]"), "[1]");
+        assert_eq!(run("{
+            two = one + 1;
+            zero = 0;
+            one = zero + 1;
+            \"result\": two, \"null\": zero
+        }"), "{ \"result\": 2, \"null\": 0 }");
+        assert_eq!(run("{ 3, 1, 2, 1 }"), "{ 1, 2, 3 }");
+    }
+
+    #[test]
+    fn closures() {
+        assert_eq!(run("
+            gated_map = |x| { \"ei\": x, };
+            gated_map 0
+        "), "{ \"ei\": 0 }");
+        assert_eq!(run("{
+            fact n = ? n | 0 -> 1 | _ -> n * fact (n - 1);
+            fact 20
+        }"), "{ 2432902008176640000 }");
+        assert_eq!(run("[
+            swap (x, y) = (y, x);
+            add = (+);
+            (swap (1, 2), add 1 $ 2 * 3, ((-) 1) 2)
+        ]"), "[((2, 1), 7, -1)]");
+    }
+
+    #[test]
+    fn powers() {
+        assert_eq!(run("(2 ** 10, (-2) ** 3, 2 ** (-1), 0 ** 0)"),
+            "(1024, -8, 0.5, 1)");
+        assert_eq!(run("((-1) ** 4000000001, 1 ** 40000000000000000000)"),
+            "(-1, 1)");
+        assert_eq!(run("(2 ** 1000000) % 7"), "2");
+        assert_eq!(run("2 ** 4000000000"),
+            "`**` would give an int of over 1048576 bits");
+        assert_eq!(run("3 ** 40000000000000000000"),
+            "`**` would give an int of over 1048576 bits");
+    }
+
+    #[test]
+    fn numbers() {
+        assert_eq!(run("{ 2, 1.0, 1, 0.5, (1.0 / 0.0), (-1.0 / 0.0) }"),
+            "{ -inf, 0.5, 1, 1.0, 2, inf }");
+        assert_eq!(run("{ 9007199254740993, 9007199254740992.0 }"),
+            "{ 9007199254740992.0, 9007199254740993 }");
+        assert_eq!(run("{ 1: \"int\", 1.0: \"float\" }"),
+            "{ 1: \"int\", 1.0: \"float\" }");
+        // operators and patterns compare numbers by magnitude alone
+        assert_eq!(run("(1 == 1.0, 1 <= 1.0, 9007199254740993 > 2.0 ** 53)"),
+            "(1, 1, 1)");
+        assert_eq!(run("? 0.0 | 0 -> 1 | _ -> 2"), "1");
+        // within compound values too
+        assert_eq!(run("((1, 2) == (1.0, 2), [1, 2.5] < [1.0, 3])"), "(1, 1)");
+        assert_eq!(run("({ 1: [2] } == { 1.0: [2.0] }, { 1 } != { 1.0 })"),
+            "(1, 0)");
+    }
+
+    #[test]
+    fn recursion() {
+        let text = "{ f n := ? n | 0 -> 0 | _ -> f (n - 1); f 1000 }";
+        assert_eq!(run(text), "{ 0 }");
+        assert_eq!(run(&text.replace("1000", "100000")),
+            "evaluation nested too deeply");
+        // workers are nested as deep
+        let text = "{ f n := ? n | 0 -> 0 | _ -> (f (n - 1), 0); f 100000 }";
+        assert_eq!(run_with(text, 4), vec!["evaluation nested too deeply"]);
+    }
+
+    #[test]
+    fn exposures() {
+        assert_eq!(run("[
+            x = 1;
+            y = x + 1;
+            blk = [ x = x; y := y; x, x, x, y, y ];
+            blk' = blk;
+            (blk', blk'.y)
+        ]"), "[([1, 1, 1, 2, 2], 2)]");
+        assert_eq!(run("[
+            <x; y> = { x := 1; y := 2; };
+            <..> := ( <..> := (z := x + y;); );
+            z
+        ]"), "[3]");
+        // `<..>` doesn't hide the outer scopes
+        assert_eq!(run("[ m = { x := 1; y := 2; }; ( <..> = m; x + y ) ]"),
+            "[3]");
+        assert_eq!(run("[
+            m = { x := 1; y := 2; };
+            z = 5;
+            ( <..> = m; (x, z) )
+        ]"), "[(1, 5)]");
+        assert_eq!(run("[ z = 5; ( <..> = { z := 1; }; z ) ]"), "[1]");
+        assert_eq!(run("[ 1 2 ]"), "int can't be applied");
+    }
+
+    #[test]
+    fn laziness() {
+        assert_eq!(run("
+            <a> = {
+                undone := undone;
+                a := 1;
+            };
+            a
+        "), "1");
+        assert_eq!(run("[ never = 1 / 0; <b> = { a := b; b := a; }; 2 ]"),
+            "[2]");
+        assert_eq!(run("[ <b> = { a := b; b := a; }; b ]"),
+            "`b` depends on itself");
+        assert_eq!(run("{ (x, y) = (1, 2); z = x + y; z }"), "{ 3 }");
+    }
+
+    #[test]
+    fn parallel() {
+        let text = "{
+            fib n = ? n | 0 -> 0 | 1 -> 1 | _ -> fib (n - 1) + fib (n - 2);
+            shared = fib 15;
+            (shared, fib 10, shared + 1, (fib 5, fib 6), [shared])
+        }";
+        for threads in [1, 2, 4, 16] {
+            assert_eq!(run_with(text, threads),
+                vec!["{ (610, 55, 611, (5, 8), [610]) }"]);
+        }
+        let text = "(1 / 0, 2, \"a\" + 1, (3, 4 5))";
+        for threads in [1, 4] {
+            assert_eq!(run_with(text, threads), vec![
+                "division by zero",
+                "`+` can't be applied to string and int",
+                "int can't be applied",
+            ]);
+        }
+        assert_eq!(run_with("{ x = (1, x); x }", 4),
+            vec!["`x` depends on itself"]);
+        let diags = run_with("[ <a; b> = { a := b; b := a; }; (a, b) ]", 4);
+        assert_eq!(diags.len(), 2);
+        assert!(diags.iter().all(|d| d.ends_with("depends on itself")));
+    }
+}
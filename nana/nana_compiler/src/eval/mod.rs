@@ -0,0 +1,9 @@
+pub mod value;
+pub mod env;
+mod builtin;
+mod pool;
+#[allow(clippy::module_inception)]
+pub mod eval;
+
+pub use eval::{eval, eval_with, Config};
+pub use value::Value;
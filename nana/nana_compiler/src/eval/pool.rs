@@ -0,0 +1,88 @@
+//! Threads shared by the evaluation, for the values of `()` blocks which
+//! are evaluated in parallel.
+//!
+//! Each thread runs a task. A task blocks either on a binding another task
+//! is evaluating, or on the workers it spawned for a block. Before blocking
+//! on a binding, a task checks that it's not waited for in turn, which
+//! would otherwise deadlock; it's then a cycle of bindings.
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+pub struct Pool {
+    /// Threads that may be spawned besides those running.
+    budget: AtomicUsize,
+    /// Ids of the tasks started so far.
+    tasks: AtomicUsize,
+    /// The tasks each blocked task waits for.
+    waits: Mutex<HashMap<usize, Vec<usize>>>,
+}
+
+impl Pool {
+    /// A pool running at most `width` threads at a time.
+    pub fn new(width: usize) -> Self {
+        Self {
+            budget: AtomicUsize::new(width.saturating_sub(1)),
+            tasks: AtomicUsize::new(0),
+            waits: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// A fresh id of task.
+    pub fn task(&self) -> usize {
+        self.tasks.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Reserves workers for `n` jobs, to replace the running thread which
+    /// then blocks on them; none if not worth it.
+    pub fn reserve(&self, n: usize) -> usize {
+        if n < 2 {
+            return 0
+        }
+        let taken = self.budget.fetch_update(
+            Ordering::AcqRel, Ordering::Acquire,
+            |budget| (budget > 0).then_some(budget - budget.min(n - 1)),
+        );
+        match taken {
+            Ok(budget) => budget.min(n - 1) + 1,
+            Err(_) => 0,
+        }
+    }
+
+    /// Releases the workers reserved, once joined.
+    pub fn release(&self, workers: usize) {
+        self.budget.fetch_add(workers - 1, Ordering::AcqRel);
+    }
+
+    /// Blocks the task on its workers, which can't wait for it before.
+    pub fn join(&self, task: usize, workers: Vec<usize>) {
+        self.waits.lock().expect("poisoned").insert(task, workers);
+    }
+
+    /// Blocks the task on the task evaluating a binding, unless it's
+    /// waited for by it, in which case `false` is returned.
+    pub fn block(&self, task: usize, on: usize) -> bool {
+        let mut waits = self.waits.lock().expect("poisoned");
+        let mut stack = vec![on];
+        let mut seen = vec![on];
+        while let Some(t) = stack.pop() {
+            if t == task {
+                return false
+            }
+            for &next in waits.get(&t).into_iter().flatten() {
+                if !seen.contains(&next) {
+                    seen.push(next);
+                    stack.push(next);
+                }
+            }
+        }
+        waits.insert(task, vec![on]);
+        true
+    }
+
+    /// Resumes the task blocked.
+    pub fn resume(&self, task: usize) {
+        self.waits.lock().expect("poisoned").remove(&task);
+    }
+}
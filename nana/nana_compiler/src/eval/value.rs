@@ -0,0 +1,294 @@
+//! Values of nana, as evaluated.
+
+use super::env::{Env, Slot};
+use crate::base::{Binder, Inner, Raw, Span};
+use crate::resolve::ast::GatedBlock;
+use num_bigint::BigInt;
+use num_traits::FromPrimitive;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::{Arc, OnceLock};
+
+#[derive(Clone)]
+pub enum Value {
+    Unit,
+    Int(BigInt),
+    Float(f64),
+    Str(String),
+    Raw(Raw),
+    Tuple(Vec<Value>),
+    List(Vec<Value>),
+    Set(BTreeSet<Value>),
+    /// Pairs in the order written, with unique keys.
+    Map(Vec<(Value, Value)>),
+    Closure(Arc<Closure>),
+    /// A value evaluated by a block or bound to a binder, which is its
+    /// value otherwise.
+    Block(Arc<Block>),
+}
+
+pub enum Closure {
+    /// A gated block with the arguments applied so far.
+    Gated {
+        gate: Arc<GatedBlock>,
+        /// The captured bindings.
+        env: Env,
+        args: Vec<Value>,
+    },
+    /// An operator with the arguments applied so far.
+    Builtin {
+        op: String,
+        args: Vec<Value>,
+    },
+}
+
+pub struct Block {
+    /// The exposed bindings, evaluated when projected or exposed.
+    pub space: BTreeMap<String, Arc<Slot>>,
+    pub value: Value,
+    /// The block evaluated to it, if any.
+    pub span: Option<Span>,
+    /// The binder first bound to it, shared by wherever it's referred to.
+    pub name: OnceLock<String>,
+}
+
+impl Value {
+    /// Sees through a block to its value.
+    pub fn peel(&self) -> &Value {
+        match self {
+            Value::Block(b) => b.value.peel(),
+            v => v,
+        }
+    }
+
+    /// Names a compound value by the binder bound to, so that it's known
+    /// as the same value wherever referred to.
+    pub fn named(self, name: &str) -> Value {
+        let value = match self {
+            Value::Block(b) => {
+                let _ = b.name.set(name.to_owned());
+                return Value::Block(b)
+            }
+            Value::Tuple(_) | Value::List(_) | Value::Set(_) | Value::Map(_)
+                => self,
+            v => return v,
+        };
+        let name = OnceLock::from(name.to_owned());
+        let space = BTreeMap::new();
+        Value::Block(Arc::new(Block { space, value, span: None, name }))
+    }
+
+    /// The span of the block the value is evaluated by, if any.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Value::Block(b) => b.span.or_else(|| b.value.span()),
+            _ => None,
+        }
+    }
+
+    /// The name of the value, if bound to a binder.
+    pub fn name(&self) -> Option<&str> {
+        match self {
+            Value::Block(b) => b.name.get().map(String::as_str),
+            _ => None,
+        }
+    }
+
+    pub fn kind(&self) -> &'static str {
+        match self.peel() {
+            Value::Unit => "unit",
+            Value::Int(_) => "int",
+            Value::Float(_) => "float",
+            Value::Str(_) => "string",
+            Value::Raw(_) => "raw",
+            Value::Tuple(_) => "tuple",
+            Value::List(_) => "list",
+            Value::Set(_) => "set",
+            Value::Map(_) => "map",
+            Value::Closure(_) => "closure",
+            Value::Block(_) => unreachable!("peeled"),
+        }
+    }
+
+    /// Compares as ordered, except that numbers are compared by magnitude
+    /// alone, as by operators, within compound values too.
+    pub fn compare(&self, other: &Self) -> Ordering {
+        match (self.peel(), other.peel()) {
+            (Value::Int(a), Value::Float(b)) => int_float(a, *b),
+            (Value::Float(a), Value::Int(b)) => int_float(b, *a).reverse(),
+            (Value::Tuple(a), Value::Tuple(b))
+            | (Value::List(a), Value::List(b)) => {
+                compare_all(a.iter(), b.iter())
+            }
+            (Value::Set(a), Value::Set(b)) => compare_all(a.iter(), b.iter()),
+            (Value::Map(a), Value::Map(b)) => a.iter().zip(b)
+                .map(|((ka, va), (kb, vb))| {
+                    ka.compare(kb).then_with(|| va.compare(vb))
+                })
+                .find(|o| o.is_ne())
+                .unwrap_or_else(|| a.len().cmp(&b.len())),
+            (a, b) => a.cmp(b),
+        }
+    }
+
+    /// Truth of ints, as returned by comparisons.
+    pub fn bool(b: bool) -> Value {
+        Value::Int(BigInt::from(b as u8))
+    }
+
+    fn rank(&self) -> u8 {
+        match self {
+            Value::Unit => 0,
+            Value::Int(_) | Value::Float(_) => 1,
+            Value::Str(_) => 2,
+            Value::Raw(_) => 3,
+            Value::Tuple(_) => 4,
+            Value::List(_) => 5,
+            Value::Set(_) => 6,
+            Value::Map(_) => 7,
+            Value::Closure(_) => 8,
+            Value::Block(_) => unreachable!("peeled"),
+        }
+    }
+}
+
+/// Compares the values in order by `Value::compare`, then by count.
+fn compare_all<'a, I>(a: I, b: I) -> Ordering
+where I: ExactSizeIterator<Item = &'a Value> {
+    let len = a.len().cmp(&b.len());
+    a.zip(b).map(|(x, y)| x.compare(y)).find(|o| o.is_ne()).unwrap_or(len)
+}
+
+/// Compares an int with a float exactly; NaNs are beyond the infinities
+/// of their sign, as by `f64::total_cmp`.
+fn int_float(a: &BigInt, b: f64) -> Ordering {
+    if b.is_nan() || b.is_infinite() {
+        return 0f64.total_cmp(&b)
+    }
+    let trunc = b.trunc();
+    let int = BigInt::from_f64(trunc).expect("finite");
+    a.cmp(&int).then_with(|| trunc.total_cmp(&b))
+}
+
+/// A total order, so that values can be in sets or keys of maps; numbers
+/// are compared by magnitude, an int before a float as large, closures by
+/// identity. Thus `1` and `1.0` are different keys.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (lhs, rhs) = (self.peel(), other.peel());
+        match (lhs, rhs) {
+            (Value::Int(a), Value::Int(b)) => a.cmp(b),
+            (Value::Float(a), Value::Float(b)) => a.total_cmp(b),
+            (Value::Int(a), Value::Float(b)) => {
+                int_float(a, *b).then(Ordering::Less)
+            }
+            (Value::Float(a), Value::Int(b)) => {
+                int_float(b, *a).reverse().then(Ordering::Greater)
+            }
+            (Value::Str(a), Value::Str(b)) => a.cmp(b),
+            (Value::Raw(a), Value::Raw(b)) => {
+                (&a.tag, &a.text).cmp(&(&b.tag, &b.text))
+            }
+            (Value::Tuple(a), Value::Tuple(b)) => a.cmp(b),
+            (Value::List(a), Value::List(b)) => a.cmp(b),
+            (Value::Set(a), Value::Set(b)) => a.cmp(b),
+            (Value::Map(a), Value::Map(b)) => a.cmp(b),
+            (Value::Closure(a), Value::Closure(b)) => {
+                Arc::as_ptr(a).cmp(&Arc::as_ptr(b))
+            }
+            _ => lhs.rank().cmp(&rhs.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Value {}
+
+/// Printing values in nana.
+mod print {
+    use super::*;
+
+    fn seq<'a, I>(f: &mut fmt::Formatter<'_>, values: I) -> fmt::Result
+    where I: IntoIterator<Item = &'a Value> {
+        for (i, v) in values.into_iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", v)?;
+        }
+        Ok(())
+    }
+
+    impl fmt::Display for Value {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Value::Unit => write!(f, "()"),
+                Value::Int(i) => write!(f, "{}", i),
+                Value::Float(x) => write!(f, "{:?}", x),
+                Value::Str(s) => write!(f, "{:?}", s),
+                Value::Raw(r) => write!(f, "{}", r),
+                Value::Tuple(vs) => {
+                    write!(f, "(")?;
+                    seq(f, vs)?;
+                    write!(f, ")")
+                }
+                Value::List(vs) => {
+                    write!(f, "[")?;
+                    seq(f, vs)?;
+                    write!(f, "]")
+                }
+                Value::Set(vs) if vs.is_empty() => write!(f, "{{}}"),
+                Value::Set(vs) => {
+                    write!(f, "{{ ")?;
+                    seq(f, vs)?;
+                    write!(f, " }}")
+                }
+                Value::Map(kvs) if kvs.is_empty() => write!(f, "{{}}"),
+                Value::Map(kvs) => {
+                    write!(f, "{{ ")?;
+                    for (i, (k, v)) in kvs.iter().enumerate() {
+                        if i > 0 {
+                            write!(f, ", ")?;
+                        }
+                        write!(f, "{}: {}", k, v)?;
+                    }
+                    write!(f, " }}")
+                }
+                Value::Closure(c) => write!(f, "{:?}", c),
+                Value::Block(b) => write!(f, "{}", b.value),
+            }
+        }
+    }
+
+    impl fmt::Debug for Value {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            write!(f, "{}", self)
+        }
+    }
+
+    impl fmt::Debug for Closure {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                Closure::Gated { gate, args, .. } => {
+                    let traces: Vec<_> = gate.traces[args.len()..].iter()
+                        .map(|t| Binder::as_str(&t.inner_ref().binder))
+                        .collect();
+                    write!(f, "|{}| ..", traces.join(", "))
+                }
+                Closure::Builtin { op, .. } => write!(f, "({})", op),
+            }
+        }
+    }
+}
@@ -4,36 +4,75 @@ pub use crate::base::*;
 
 #[derive(Clone, Debug)]
 pub struct Nana {
-    pub body: GatedBlock,
+    pub body: Spanned<GatedBlock>,
 }
 
 #[derive(Clone, Debug)]
 pub struct GatedBlock {
-    pub traces: Vec<Binder>,
-    pub block: Block
+    pub traces: Vec<Spanned<Binder>>,
+    pub block: Spanned<Block>,
 }
 
 #[derive(Clone, Debug)]
 pub enum Block {
-    Tuple(Vec<Abstraction>, Vec<Expr>),
-    List(Vec<Abstraction>, Vec<Expr>),
-    Set(Vec<Abstraction>, Vec<Expr>),
-    // Map(Vec<Abstraction>, Vec<Pair>),
+    Tuple(Vec<Spanned<Abstraction>>, Vec<Spanned<Expr>>),
+    List(Vec<Spanned<Abstraction>>, Vec<Spanned<Expr>>),
+    Set(Vec<Spanned<Abstraction>>, Vec<Spanned<Expr>>),
+    Map(Vec<Spanned<Abstraction>>, Vec<Spanned<Pair>>),
 }
 
-pub type BlockInner = (Vec<Abstraction>, Vec<Expr>);
+pub type BlockInner = (Vec<Spanned<Abstraction>>, Vec<Spanned<Expr>>);
 
 #[derive(Clone, Debug)]
 pub struct Abstraction {
-    pub trace: Binder,
+    pub head: Spanned<Head>,
     pub exposed: bool,
-    pub src: Expr,
+    pub src: Spanned<Expr>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Head {
+    /// `f x y`, a function binding.
+    Fun(Spanned<Binder>, Vec<Spanned<Pattern>>),
+    Pat(Spanned<Pattern>),
+}
+
+#[derive(Clone, Debug)]
+pub struct Branch {
+    pub pattern: Spanned<Pattern>,
+    pub body: Spanned<Expr>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    /// `(alias = pattern)`
+    Alias(Box<Spanned<Pattern>>, Box<Spanned<Pattern>>),
+    Wild,
+    Forall,
+    Literal(Literal),
+    Binder(Binder),
+    Exposure(Vec<Spanned<ExposurePattern>>),
+    Tuple(Vec<Spanned<Pattern>>),
+    List(Vec<Spanned<Pattern>>),
+    Map(Vec<Spanned<PairPattern>>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ExposurePattern {
+    Binder(Binder),
+    All,
+}
+
+#[derive(Clone, Debug)]
+pub struct PairPattern {
+    pub key: Spanned<Expr>,
+    pub val: Spanned<Pattern>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Pair {
-    pub key: Expr,
-    pub val: Expr,
+    pub key: Spanned<Expr>,
+    pub val: Spanned<Expr>,
 }
 
 #[derive(Clone, Debug)]
@@ -42,37 +81,35 @@ pub enum Expr {
     Binder(Binder),
     Block(Block),
     GatedBlock(GatedBlock),
-    Application(Box<Expr>, Box<Expr>),
-    Projection(Box<Expr>, Binder),
+    Application(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    Projection(Box<Spanned<Expr>>, Spanned<Binder>),
+    /// `? e | p -> e`
+    Matching(Box<Spanned<Expr>>, Vec<Spanned<Branch>>),
+    /// Left by the parser where a syntax error is recovered.
+    Error,
 }
 
 mod construct {
     use super::*;
 
-    impl From<BlockInner> for Nana {
-        fn from(bi: BlockInner) -> Self {
-            Self { body: bi.into() }
+    impl From<Spanned<BlockInner>> for Nana {
+        fn from(bi: Spanned<BlockInner>) -> Self {
+            let span = bi.span();
+            let body = GatedBlock::from(bi.map(Block::from));
+            Self { body: Node::new(body, span) }
         }
     }
 
-    impl From<BlockInner> for GatedBlock {
-        fn from(bi: BlockInner) -> Self {
-            Block::from(bi).into()
-        }
-    }
-    impl From<Expr> for GatedBlock {
-        fn from(e: Expr) -> Self {
-            Block::from(e).into()
-        }
-    }
-    impl From<Block> for GatedBlock {
-        fn from(block: Block) -> Self {
+    impl From<Spanned<Block>> for GatedBlock {
+        fn from(block: Spanned<Block>) -> Self {
             (Vec::new(), block).into()
         }
     }
     /// Insert from last to first.
-    impl From<(Vec<Binder>, Block)> for GatedBlock {
-        fn from((traces, block): (Vec<Binder>, Block)) -> Self {
+    impl From<(Vec<Spanned<Binder>>, Spanned<Block>)> for GatedBlock {
+        fn from(
+            (traces, block): (Vec<Spanned<Binder>>, Spanned<Block>)
+        ) -> Self {
             Self { traces, block }
         }
     }
@@ -82,25 +119,40 @@ mod construct {
             Block::Tuple(bds, vls)
         }
     }
-    impl From<Expr> for Block {
-        fn from(e: Expr) -> Self {
+    impl From<Spanned<Expr>> for Block {
+        fn from(e: Spanned<Expr>) -> Self {
             Block::Tuple(Vec::new(), vec![e])
         }
     }
-    impl From<GatedBlock> for Block {
-        fn from(e: GatedBlock) -> Self {
-            Expr::GatedBlock(e).into()
+
+    impl From<(Spanned<Head>, bool, Spanned<Expr>)> for Abstraction {
+        fn from(
+            (head, exposed, src): (Spanned<Head>, bool, Spanned<Expr>)
+        ) -> Self {
+            Self { head, exposed, src }
         }
     }
 
-    impl From<(Binder, bool, Expr)> for Abstraction {
-        fn from((trace, exposed, src): (Binder, bool, Expr)) -> Self {
-            Self { trace, exposed, src }
+    impl From<(Spanned<Pattern>, Spanned<Expr>)> for Branch {
+        fn from((pattern, body): (Spanned<Pattern>, Spanned<Expr>)) -> Self {
+            Self { pattern, body }
+        }
+    }
+
+    impl From<(Spanned<Pattern>, Spanned<Pattern>)> for Pattern {
+        fn from((alias, pat): (Spanned<Pattern>, Spanned<Pattern>)) -> Self {
+            Self::Alias(Box::new(alias), Box::new(pat))
+        }
+    }
+
+    impl From<(Spanned<Expr>, Spanned<Pattern>)> for PairPattern {
+        fn from((key, val): (Spanned<Expr>, Spanned<Pattern>)) -> Self {
+            Self { key, val }
         }
     }
 
-    impl From<(Expr, Expr)> for Pair {
-        fn from((key, val): (Expr, Expr)) -> Self {
+    impl From<(Spanned<Expr>, Spanned<Expr>)> for Pair {
+        fn from((key, val): (Spanned<Expr>, Spanned<Expr>)) -> Self {
             Self { key, val }
         }
     }
@@ -1,2 +1,5 @@
 pub mod ast;
+pub mod infix;
+pub mod parse;
 pub use ast::Nana;
+pub use parse::parse;
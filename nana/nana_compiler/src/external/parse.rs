@@ -0,0 +1,33 @@
+//! Parsing source into the external ast.
+
+use super::ast::*;
+use crate::diagnostic::{parse::*, Diagnostic};
+use crate::lexer::{Guard, Lexer};
+use crate::nana;
+
+/// Parses the source, recovering from as many syntax errors as possible.
+///
+/// Returns the (possibly partial) ast, which is `None` only if the parser
+/// fails to recover, along with all diagnostics reported.
+pub fn parse(src: &SrcFile) -> (Option<Nana>, Vec<Diagnostic>) {
+    let mut errors = Vec::new();
+    let mut lexer = Lexer::new(&src.text);
+    let tokens = Guard::new(&mut lexer);
+    let res = nana::NanaParser::new().parse(&mut errors, tokens);
+    let mut diags: Vec<Diagnostic> = lexer
+        .take_errors()
+        .into_iter()
+        .map(from_lex_error)
+        .collect();
+    diags.extend(errors.into_iter().map(from_error_recovery));
+    let nana = match res {
+        Ok(nana) => Some(nana),
+        Err(e) => {
+            diags.push(from_parse_error(e));
+            None
+        }
+    };
+    // lexing errors are taken apart from parsing ones
+    diags.sort_by_key(|d| (d.span().is_none(), d.span().map(|s| s.start)));
+    (nana, diags)
+}
@@ -5,34 +5,66 @@ pub use crate::base::*;
 
 #[derive(Clone, Debug)]
 pub struct Nana {
-    pub body: GatedBlock,
+    pub body: Spanned<GatedBlock>,
 }
 
 #[derive(Clone, Debug)]
 pub struct GatedBlock {
-    pub traces: Vec<Binder>,
-    pub block: Block
+    pub traces: Vec<Spanned<Binder>>,
+    pub block: Spanned<Block>,
 }
 
 #[derive(Clone, Debug)]
 pub enum Block {
-    Tuple(Vec<Abstraction>, Vec<Expr>),
-    List(Vec<Abstraction>, Vec<Expr>),
-    Set(Vec<Abstraction>, Vec<Expr>),
-    // Map(Vec<Abstraction>, Vec<Pair>),
+    Tuple(Vec<Spanned<Abstraction>>, Vec<Spanned<Expr>>),
+    List(Vec<Spanned<Abstraction>>, Vec<Spanned<Expr>>),
+    Set(Vec<Spanned<Abstraction>>, Vec<Spanned<Expr>>),
+    Map(Vec<Spanned<Abstraction>>, Vec<Spanned<Pair>>),
 }
 
 #[derive(Clone, Debug)]
 pub struct Abstraction {
-    pub trace: Binder,
+    pub trace: Spanned<Pattern>,
     pub exposed: bool,
-    pub src: GatedBlock,
+    pub src: Spanned<GatedBlock>,
+}
+
+/// Single tuples of patterns are reduced as well.
+#[derive(Clone, Debug)]
+pub struct Branch {
+    pub pattern: Spanned<Pattern>,
+    pub body: Spanned<Expr>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    Alias(Box<Spanned<Pattern>>, Box<Spanned<Pattern>>),
+    Wild,
+    Forall,
+    Literal(Literal),
+    Binder(Binder),
+    Exposure(Vec<Spanned<ExposurePattern>>),
+    Tuple(Vec<Spanned<Pattern>>),
+    List(Vec<Spanned<Pattern>>),
+    Map(Vec<Spanned<PairPattern>>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ExposurePattern {
+    Binder(Binder),
+    All,
+}
+
+#[derive(Clone, Debug)]
+pub struct PairPattern {
+    pub key: Spanned<Expr>,
+    pub val: Spanned<Pattern>,
 }
 
 #[derive(Clone, Debug)]
 pub struct Pair {
-    pub key: Expr,
-    pub val: Expr,
+    pub key: Spanned<Expr>,
+    pub val: Spanned<Expr>,
 }
 
 #[derive(Clone, Debug)]
@@ -40,6 +72,10 @@ pub enum Expr {
     Literal(Literal),
     Binder(Binder),
     GatedBlock(GatedBlock),
-    Application(Box<Expr>, Box<Expr>),
-    Projection(Box<Expr>, Binder),
+    Application(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    Projection(Box<Spanned<Expr>>, Spanned<Binder>),
+    /// `? e | p -> e`
+    Matching(Box<Spanned<Expr>>, Vec<Spanned<Branch>>),
+    /// Recovered from a syntax error; reported by `resolve`.
+    Error,
 }
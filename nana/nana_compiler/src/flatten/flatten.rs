@@ -2,6 +2,7 @@
 
 use super::ast as fa;
 use crate::external::ast as ea;
+use crate::base::*;
 
 /// Flatten all tuples; add gate to all blocks.
 pub trait Flatten<T> {
@@ -14,37 +15,36 @@ impl Flatten<fa::Nana> for ea::Nana {
     }
 }
 
-impl Flatten<fa::GatedBlock> for ea::GatedBlock {
-    fn flatten(self) -> fa::GatedBlock {
-        fa::GatedBlock {
-            traces: self.traces,
-            block: self.block.flatten(),
-        }
+impl Flatten<Spanned<fa::GatedBlock>> for Spanned<ea::GatedBlock> {
+    fn flatten(self) -> Spanned<fa::GatedBlock> {
+        self.map(|g| fa::GatedBlock {
+            traces: g.traces,
+            block: g.block.flatten(),
+        })
     }
 }
 
-impl Flatten<fa::Expr> for ea::GatedBlock {
-        // println!("{:#?}", self);
-    fn flatten(self) -> fa::Expr {
-        let traces = self.traces;
-        let block = self.block;
-        match (traces.is_empty(), block) {
-            (true, ea::Block::Tuple(bds, vls)) => {
-                ea::Block::Tuple(bds, vls).flatten()
-            }
-            (_, block) => {
-                fa::Expr::GatedBlock(fa::GatedBlock {
+impl Flatten<Spanned<fa::Expr>> for Spanned<ea::GatedBlock> {
+    fn flatten(self) -> Spanned<fa::Expr> {
+        let span = self.span();
+        let ea::GatedBlock { traces, block } = self.inner();
+        match (traces.is_empty(), block.inner_ref()) {
+            (true, ea::Block::Tuple(_, _)) => {
+                block.flatten()
+            }
+            (_, _) => {
+                Node::new(fa::Expr::GatedBlock(fa::GatedBlock {
                     traces,
                     block: block.flatten(),
-                })
+                }), span)
             }
         }
     }
 }
 
-impl Flatten<fa::Block> for ea::Block {
-    fn flatten(self) -> fa::Block {
-        match self {
+impl Flatten<Spanned<fa::Block>> for Spanned<ea::Block> {
+    fn flatten(self) -> Spanned<fa::Block> {
+        self.map(|block| match block {
             ea::Block::Tuple(bds, vls) => {
                 fa::Block::Tuple(
                     bds.into_iter().map(|x| x.flatten()).collect(),
@@ -63,66 +63,222 @@ impl Flatten<fa::Block> for ea::Block {
                     vls.into_iter().map(|x| x.flatten()).collect()
                 )
             }
-        }
+            ea::Block::Map(bds, vls) => {
+                fa::Block::Map(
+                    bds.into_iter().map(|x| x.flatten()).collect(),
+                    vls.into_iter().map(|x| x.flatten()).collect()
+                )
+            }
+        })
     }
 }
 
-impl Flatten<fa::Abstraction> for ea::Abstraction {
-    fn flatten(self) -> fa::Abstraction {
-        fa::Abstraction {
-            trace: self.trace,
-            exposed: self.exposed,
-            src: self.src.flatten(),
+impl Flatten<Spanned<fa::Pair>> for Spanned<ea::Pair> {
+    fn flatten(self) -> Spanned<fa::Pair> {
+        self.map(|pair| fa::Pair {
+            key: pair.key.flatten(),
+            val: pair.val.flatten(),
+        })
+    }
+}
+
+impl Flatten<Spanned<fa::Abstraction>> for Spanned<ea::Abstraction> {
+    fn flatten(self) -> Spanned<fa::Abstraction> {
+        self.map(|abs| {
+            let ea::Abstraction { head, exposed, src } = abs;
+            match head.inner() {
+                ea::Head::Pat(p) => fa::Abstraction {
+                    trace: p.flatten(),
+                    exposed,
+                    src: src.flatten(),
+                },
+                ea::Head::Fun(binder, args) => fa::Abstraction {
+                    trace: binder.map(fa::Pattern::Binder),
+                    exposed,
+                    src: curry(args, src.flatten()),
+                },
+            }
+        })
+    }
+}
+
+/// Desugars the parameters of a function binding into nested gated blocks,
+/// one trace each. A parameter other than a binder is traced by a fresh
+/// `arg#i` instead, which is then destructured in the block.
+fn curry(
+    args: Vec<Spanned<ea::Pattern>>, body: Spanned<fa::GatedBlock>
+) -> Spanned<fa::GatedBlock> {
+    // no parameters, as the grammar never gives, leave the body as is
+    let arity = args.len();
+    args.into_iter().enumerate().rev().fold(body, |body, (i, arg)| {
+        let span = arg.span().join(body.span());
+        let arg = arg.flatten();
+        let (trace, bds) = match arg.inner_ref() {
+            fa::Pattern::Binder(b) => (Node::new(b.clone(), arg.span()), None),
+            pat => {
+                let trace = Node::new(
+                    Binder::from(format!("arg#{}", i)),
+                    arg.span()
+                );
+                let src = Node::new(
+                    fa::Expr::Binder(trace.inner_ref().clone()),
+                    arg.span()
+                );
+                let bd = fa::Abstraction {
+                    trace: arg.clone(),
+                    exposed: false,
+                    src: Node::new(fa::GatedBlock {
+                        traces: Vec::new(),
+                        block: Node::new(
+                            fa::Block::Tuple(Vec::new(), vec![src]),
+                            arg.span()
+                        ),
+                    }, arg.span()),
+                };
+                match pat {
+                    fa::Pattern::Wild => (trace, None),
+                    _ => (trace, Some(Node::new(bd, arg.span()))),
+                }
+            }
+        };
+        let innermost = i + 1 == arity;
+        if innermost && body.inner_ref().traces.is_empty() && bds.is_none() {
+            // the body block itself is gated
+            body.map(|mut g| {
+                g.traces.push(trace);
+                g
+            })
+        } else {
+            let inner = body.map(fa::Expr::GatedBlock);
+            Node::new(fa::GatedBlock {
+                traces: vec![trace],
+                block: Node::new(
+                    fa::Block::Tuple(bds.into_iter().collect(), vec![inner]),
+                    span
+                ),
+            }, span)
         }
+    })
+}
+
+impl Flatten<Spanned<fa::Branch>> for Spanned<ea::Branch> {
+    fn flatten(self) -> Spanned<fa::Branch> {
+        self.map(|branch| fa::Branch {
+            pattern: branch.pattern.flatten(),
+            body: branch.body.flatten(),
+        })
+    }
+}
+
+impl Flatten<Spanned<fa::Pattern>> for Spanned<ea::Pattern> {
+    fn flatten(self) -> Spanned<fa::Pattern> {
+        let span = self.span();
+        let pat = match self.inner() {
+            ea::Pattern::Alias(al, p) => {
+                fa::Pattern::Alias(Box::new(al.flatten()), Box::new(p.flatten()))
+            }
+            ea::Pattern::Wild => fa::Pattern::Wild,
+            ea::Pattern::Forall => fa::Pattern::Forall,
+            ea::Pattern::Literal(l) => fa::Pattern::Literal(l),
+            ea::Pattern::Binder(b) => fa::Pattern::Binder(b),
+            ea::Pattern::Exposure(ex) => {
+                fa::Pattern::Exposure(
+                    ex.into_iter().map(|x| x.map(|ex| match ex {
+                        ea::ExposurePattern::Binder(b) => {
+                            fa::ExposurePattern::Binder(b)
+                        }
+                        ea::ExposurePattern::All => fa::ExposurePattern::All,
+                    })).collect()
+                )
+            }
+            ea::Pattern::Tuple(mut ps) if ps.len() == 1 => {
+                return ps.remove(0).flatten()
+            }
+            ea::Pattern::Tuple(ps) => {
+                fa::Pattern::Tuple(ps.into_iter().map(|x| x.flatten()).collect())
+            }
+            ea::Pattern::List(ps) => {
+                fa::Pattern::List(ps.into_iter().map(|x| x.flatten()).collect())
+            }
+            ea::Pattern::Map(ps) => {
+                fa::Pattern::Map(ps.into_iter().map(|x| x.flatten()).collect())
+            }
+        };
+        Node::new(pat, span)
+    }
+}
+
+impl Flatten<Spanned<fa::PairPattern>> for Spanned<ea::PairPattern> {
+    fn flatten(self) -> Spanned<fa::PairPattern> {
+        self.map(|pair| fa::PairPattern {
+            key: pair.key.flatten(),
+            val: pair.val.flatten(),
+        })
     }
 }
 
-impl Flatten<fa::GatedBlock> for ea::Expr {
-    fn flatten(self) -> fa::GatedBlock {
-        match self {
+impl Flatten<Spanned<fa::GatedBlock>> for Spanned<ea::Expr> {
+    fn flatten(self) -> Spanned<fa::GatedBlock> {
+        let span = self.span();
+        match self.inner() {
             ea::Expr::Block(block) => {
-                fa::GatedBlock {
+                Node::new(fa::GatedBlock {
                     traces: Vec::new(),
-                    block: block.flatten(),
-                }
+                    block: Node::new(block, span).flatten(),
+                }, span)
             }
-            ea::Expr::GatedBlock(g) => g.flatten(),
-            ea::Expr::Literal(_) |
-            ea::Expr::Binder(_) |
-            ea::Expr::Application(_, _) |
-            ea::Expr::Projection(_, _) => {
-                fa::GatedBlock {
+            ea::Expr::GatedBlock(g) => Node::new(g, span).flatten(),
+            e @ ea::Expr::Literal(_) |
+            e @ ea::Expr::Binder(_) |
+            e @ ea::Expr::Application(_, _) |
+            e @ ea::Expr::Projection(_, _) |
+            e @ ea::Expr::Matching(_, _) |
+            e @ ea::Expr::Error => {
+                let e = Node::new(e, span);
+                Node::new(fa::GatedBlock {
                     traces: Vec::new(),
-                    block: fa::Block::Tuple(Vec::new(), vec![self.flatten()]),
-                }
+                    block: Node::new(
+                        fa::Block::Tuple(Vec::new(), vec![e.flatten()]),
+                        span
+                    ),
+                }, span)
             }
         }
     }
 }
 
-impl Flatten<fa::Expr> for ea::Expr {
-    fn flatten(self) -> fa::Expr {
-        match self {
-            ea::Expr::Literal(l) => fa::Expr::Literal(l),
-            ea::Expr::Binder(b) => fa::Expr::Binder(b),
-            ea::Expr::Block(block) => block.flatten(),
-            ea::Expr::GatedBlock(g) => g.flatten(),
+impl Flatten<Spanned<fa::Expr>> for Spanned<ea::Expr> {
+    fn flatten(self) -> Spanned<fa::Expr> {
+        let span = self.span();
+        match self.inner() {
+            ea::Expr::Literal(l) => Node::new(fa::Expr::Literal(l), span),
+            ea::Expr::Binder(b) => Node::new(fa::Expr::Binder(b), span),
+            ea::Expr::Block(block) => Node::new(block, span).flatten(),
+            ea::Expr::GatedBlock(g) => Node::new(g, span).flatten(),
             ea::Expr::Application(e1, e2) => {
-                fa::Expr::Application(
-                    Box::new(e1.flatten()), 
+                Node::new(fa::Expr::Application(
+                    Box::new(e1.flatten()),
                     Box::new(e2.flatten())
-                )
+                ), span)
             }
             ea::Expr::Projection(e, b) => {
-                fa::Expr::Projection(Box::new(e.flatten()), b)
+                Node::new(fa::Expr::Projection(Box::new(e.flatten()), b), span)
+            }
+            ea::Expr::Matching(e, bs) => {
+                Node::new(fa::Expr::Matching(
+                    Box::new(e.flatten()),
+                    bs.into_iter().map(|x| x.flatten()).collect()
+                ), span)
             }
+            ea::Expr::Error => Node::new(fa::Expr::Error, span),
         }
     }
 }
 
-impl Flatten<fa::Expr> for ea::Block {
-    fn flatten(self) -> fa::Expr {
-        match self {
+impl Flatten<Spanned<fa::Expr>> for Spanned<ea::Block> {
+    fn flatten(self) -> Spanned<fa::Expr> {
+        let span = self.span();
+        match self.inner_ref() {
             ea::Block::Tuple(bds, vls) => {
                 match (bds.len(), vls.len()) {
                     (0, 1) => {
@@ -130,20 +286,153 @@ impl Flatten<fa::Expr> for ea::Block {
                         e.flatten()
                     }
                     _ => {
-                        fa::Expr::GatedBlock(fa::GatedBlock {
+                        Node::new(fa::Expr::GatedBlock(fa::GatedBlock {
                             traces: Vec::new(),
-                            block: ea::Block::Tuple(bds, vls).flatten(),
-                        })
+                            block: self.flatten(),
+                        }), span)
                     }
                 }
             }
             ea::Block::List(_, _) |
-            ea::Block::Set(_, _) => {
-                fa::Expr::GatedBlock(fa::GatedBlock {
+            ea::Block::Set(_, _) |
+            ea::Block::Map(_, _) => {
+                Node::new(fa::Expr::GatedBlock(fa::GatedBlock {
                     traces: Vec::new(),
                     block: self.flatten(),
-                })
+                }), span)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external::parse;
+
+    /// The block of the body of the source.
+    fn body(text: &str) -> fa::Block {
+        let src = SrcFile::new("main.na", text);
+        let (nana, diags) = parse(&src);
+        assert!(diags.is_empty(), "{:?}", diags);
+        let nana: fa::Nana = nana.unwrap().flatten();
+        nana.body.inner().block.inner()
+    }
+
+    fn values(text: &str) -> Vec<Spanned<fa::Expr>> {
+        match body(text) {
+            fa::Block::Tuple(_, vls) => vls,
+            block => panic!("unexpected body {:?}", block),
+        }
+    }
+
+    /// The traces of nested gated blocks, outermost first.
+    fn traces(g: &fa::GatedBlock) -> Vec<String> {
+        let mut traces: Vec<String> = g.traces.iter()
+            .map(|t| t.inner_ref().as_str().to_owned())
+            .collect();
+        if let fa::Block::Tuple(_, vls) = g.block.inner_ref() {
+            if let [v] = vls.as_slice() {
+                if let fa::Expr::GatedBlock(g) = v.inner_ref() {
+                    traces.extend(self::traces(g));
+                }
+            }
+        }
+        traces
+    }
+
+    #[test]
+    fn maps() {
+        let vls = values("{ 0: m, \"k\": (n), }");
+        let pairs = match vls[0].inner_ref() {
+            fa::Expr::GatedBlock(g) => match g.block.inner_ref() {
+                fa::Block::Map(bds, pairs) if bds.is_empty() => pairs.clone(),
+                block => panic!("unexpected block {:?}", block),
+            },
+            e => panic!("unexpected expr {:?}", e),
+        };
+        assert_eq!(pairs.len(), 2);
+        // single tuples are reduced in values as well
+        assert!(matches!(
+            pairs[1].inner_ref().val.inner_ref(),
+            fa::Expr::Binder(b) if b.as_str() == "n"
+        ));
+        assert!(matches!(values("{ 0, 1 }")[0].inner_ref(),
+            fa::Expr::GatedBlock(g)
+                if matches!(g.block.inner_ref(), fa::Block::Set(_, _))
+        ));
+    }
+
+    #[test]
+    fn functions() {
+        let bds = match body("f x (a, b) _ := x;\ng y = y;\nf") {
+            fa::Block::Tuple(bds, _) => bds,
+            block => panic!("unexpected body {:?}", block),
+        };
+        let f = bds[0].inner_ref();
+        assert!(f.exposed);
+        assert!(matches!(
+            f.trace.inner_ref(),
+            fa::Pattern::Binder(b) if b.as_str() == "f"
+        ));
+        assert_eq!(traces(f.src.inner_ref()), vec!["x", "arg#1", "arg#2"]);
+        // the tuple parameter is destructured from its trace
+        let inner = match f.src.inner_ref().block.inner_ref() {
+            fa::Block::Tuple(_, vls) => match vls[0].inner_ref() {
+                fa::Expr::GatedBlock(g) => g.block.clone(),
+                e => panic!("unexpected expr {:?}", e),
+            },
+            block => panic!("unexpected block {:?}", block),
+        };
+        match inner.inner_ref() {
+            fa::Block::Tuple(bds, _) => {
+                assert_eq!(bds.len(), 1);
+                assert!(matches!(
+                    bds[0].inner_ref().trace.inner_ref(),
+                    fa::Pattern::Tuple(ps) if ps.len() == 2
+                ));
+            }
+            block => panic!("unexpected block {:?}", block),
+        }
+        let g = bds[1].inner_ref();
+        assert!(!g.exposed);
+        assert_eq!(traces(g.src.inner_ref()), vec!["y"]);
+    }
+
+    #[test]
+    fn empty_curry() {
+        let body = Node::new(fa::GatedBlock {
+            traces: Vec::new(),
+            block: Node::new(
+                fa::Block::Tuple(Vec::new(), Vec::new()),
+                Span::default()
+            ),
+        }, Span::default());
+        assert!(curry(Vec::new(), body).inner().traces.is_empty());
+    }
+
+    #[test]
+    fn matching() {
+        let vls = values("? x | 0 -> 1 | (y) -> y | _ -> x");
+        match vls[0].inner_ref() {
+            fa::Expr::Matching(e, bs) => {
+                assert!(matches!(e.inner_ref(), fa::Expr::Binder(_)));
+                assert_eq!(bs.len(), 3);
+                assert!(matches!(
+                    bs[0].inner_ref().pattern.inner_ref(),
+                    fa::Pattern::Literal(_)
+                ));
+                // a single tuple pattern is reduced
+                assert!(matches!(
+                    bs[1].inner_ref().pattern.inner_ref(),
+                    fa::Pattern::Binder(b) if b.as_str() == "y"
+                ));
+                assert!(matches!(
+                    bs[2].inner_ref().pattern.inner_ref(),
+                    fa::Pattern::Wild
+                ));
             }
+            e => panic!("unexpected expr {:?}", e),
         }
     }
 }
@@ -1,4 +1,5 @@
 pub mod ast;
+#[allow(clippy::module_inception)]
 pub mod flatten;
 
 pub use flatten::Flatten;
@@ -0,0 +1,196 @@
+//! Constructing trees from the values of each notation.
+
+use super::Tree;
+use num_bigint::BigInt;
+use serde::de::{
+    self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess,
+    VariantAccess, Visitor,
+};
+use std::fmt;
+use std::str::FromStr;
+
+impl From<bool> for Tree {
+    fn from(b: bool) -> Self {
+        Tree::Int(BigInt::from(b as u8))
+    }
+}
+
+impl From<serde_json::Value> for Tree {
+    fn from(value: serde_json::Value) -> Self {
+        use serde_json::Value;
+        match value {
+            Value::Null => Tree::Unit,
+            Value::Bool(b) => Tree::from(b),
+            // as written, integers of any size
+            Value::Number(n) => {
+                let text = n.to_string();
+                match BigInt::from_str(&text) {
+                    Ok(i) => Tree::Int(i),
+                    Err(_) => Tree::Float(n.as_f64().unwrap_or(f64::NAN)),
+                }
+            }
+            Value::String(s) => Tree::Str(s),
+            Value::Array(vs) => {
+                Tree::List(vs.into_iter().map(Tree::from).collect())
+            }
+            Value::Object(kvs) => Tree::Map(kvs.into_iter()
+                .map(|(k, v)| (Tree::Str(k), Tree::from(v)))
+                .collect()),
+        }
+    }
+}
+
+/// From YAML, with tags dropped, e.g. `!secret`, and merge keys, `<<`,
+/// left to [`merge`].
+impl<'de> Deserialize<'de> for Tree {
+    fn deserialize<D: Deserializer<'de>>(de: D) -> Result<Self, D::Error> {
+        de.deserialize_any(YamlVisitor)
+    }
+}
+
+struct YamlVisitor;
+
+impl<'de> Visitor<'de> for YamlVisitor {
+    type Value = Tree;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("any yaml value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<Tree, E> {
+        Ok(Tree::from(b))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> Result<Tree, E> {
+        Ok(Tree::Int(BigInt::from(i)))
+    }
+
+    fn visit_u64<E>(self, u: u64) -> Result<Tree, E> {
+        Ok(Tree::Int(BigInt::from(u)))
+    }
+
+    fn visit_i128<E>(self, i: i128) -> Result<Tree, E> {
+        Ok(Tree::Int(BigInt::from(i)))
+    }
+
+    fn visit_u128<E>(self, u: u128) -> Result<Tree, E> {
+        Ok(Tree::Int(BigInt::from(u)))
+    }
+
+    fn visit_f64<E>(self, x: f64) -> Result<Tree, E> {
+        Ok(Tree::Float(x))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<Tree, E> {
+        Ok(Tree::Str(s.to_owned()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<Tree, E> {
+        Ok(Tree::Str(s))
+    }
+
+    fn visit_unit<E>(self) -> Result<Tree, E> {
+        Ok(Tree::Unit)
+    }
+
+    fn visit_none<E>(self) -> Result<Tree, E> {
+        Ok(Tree::Unit)
+    }
+
+    fn visit_some<D>(self, de: D) -> Result<Tree, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Tree::deserialize(de)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Tree, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut vs = Vec::new();
+        while let Some(v) = seq.next_element()? {
+            vs.push(v);
+        }
+        Ok(Tree::List(vs))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Tree, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut kvs: Vec<(Tree, Tree)> = Vec::new();
+        while let Some((k, v)) = map.next_entry()? {
+            if kvs.iter().any(|(k2, _)| *k2 == k) {
+                return Err(de::Error::custom("duplicate key in a mapping"))
+            }
+            kvs.push((k, v));
+        }
+        Ok(Tree::Map(kvs))
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Tree, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let (_, tagged) = data.variant::<String>()?;
+        tagged.newtype_variant()
+    }
+}
+
+/// Merges the mappings under the keys `<<` into theirs, keeping the
+/// keys already there.
+pub(super) fn merge(tree: &mut Tree) -> Result<(), &'static str> {
+    match tree {
+        Tree::Map(kvs) => {
+            let key = Tree::Str("<<".to_owned());
+            if let Some(i) = kvs.iter().position(|(k, _)| *k == key) {
+                let merged = match kvs.remove(i).1 {
+                    Tree::Map(merged) => merged,
+                    Tree::List(ts) => {
+                        let mut merged = Vec::new();
+                        for t in ts {
+                            match t {
+                                Tree::Map(kvs) => merged.extend(kvs),
+                                _ => return Err(
+                                    "expected a mapping for merging"
+                                ),
+                            }
+                        }
+                        merged
+                    }
+                    _ => return Err(
+                        "expected a mapping or list of mappings for merging"
+                    ),
+                };
+                for (k, v) in merged {
+                    if kvs.iter().all(|(k2, _)| *k2 != k) {
+                        kvs.push((k, v));
+                    }
+                }
+            }
+            kvs.iter_mut().try_for_each(|(_, v)| merge(v))
+        }
+        Tree::List(ts) => ts.iter_mut().try_for_each(merge),
+        _ => Ok(()),
+    }
+}
+
+impl From<toml::Value> for Tree {
+    fn from(value: toml::Value) -> Self {
+        use toml::Value;
+        match value {
+            Value::String(s) => Tree::Str(s),
+            Value::Integer(i) => Tree::Int(BigInt::from(i)),
+            Value::Float(x) => Tree::Float(x),
+            Value::Boolean(b) => Tree::from(b),
+            Value::Datetime(dt) => Tree::Str(dt.to_string()),
+            Value::Array(vs) => {
+                Tree::List(vs.into_iter().map(Tree::from).collect())
+            }
+            Value::Table(kvs) => Tree::Map(kvs.into_iter()
+                .map(|(k, v)| (Tree::Str(k), Tree::from(v)))
+                .collect()),
+        }
+    }
+}
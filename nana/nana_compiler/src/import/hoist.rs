@@ -0,0 +1,135 @@
+//! Hoisting sub-trees found in several places into bindings.
+
+use super::print::inline;
+use super::Tree;
+use std::cmp::Reverse;
+use std::collections::{HashMap, HashSet};
+
+/// A sub-tree, found by its text.
+struct Found {
+    count: usize,
+    size: usize,
+    /// When it's first found, for ties.
+    order: usize,
+    /// The key it's first found at, if any.
+    hint: Option<String>,
+}
+
+/// Binds the largest sub-tree found in several places, of at least `size`
+/// keys and values, refers to it by the name in each of them, and again
+/// till there's none. The bindings come before those they're found in.
+pub(super) fn hoist(tree: &mut Tree, size: usize) -> Vec<(String, Tree)> {
+    let mut bindings: Vec<(String, Tree)> = Vec::new();
+    let mut names = HashSet::new();
+    loop {
+        let mut found = HashMap::new();
+        count(tree, None, &mut found);
+        for (name, tree) in bindings.iter() {
+            count(tree, Some(name), &mut found);
+        }
+        let best = found.into_iter()
+            .filter(|(_, f)| f.count > 1 && f.size >= size)
+            .max_by_key(|(_, f)| (f.size, Reverse(f.order)));
+        let (text, found) = match best {
+            Some(best) => best,
+            None => break,
+        };
+        let name = fresh(found.hint.as_deref(), &mut names);
+        let mut hoisted = None;
+        replace(tree, &text, &name, &mut hoisted);
+        for (_, tree) in bindings.iter_mut() {
+            replace(tree, &text, &name, &mut hoisted);
+        }
+        bindings.push((name, hoisted.expect("found")));
+    }
+    // smaller ones, hoisted later, are found in larger ones
+    bindings.reverse();
+    bindings
+}
+
+/// Counts the compound sub-trees, giving the size of the tree.
+fn count(
+    tree: &Tree, hint: Option<&str>, found: &mut HashMap<String, Found>
+) -> usize {
+    let size = match tree {
+        Tree::List(vs) if !vs.is_empty() => {
+            1 + vs.iter().map(|v| count(v, hint, found)).sum::<usize>()
+        }
+        Tree::Map(kvs) if !kvs.is_empty() => {
+            1 + kvs.iter()
+                .map(|(k, v)| {
+                    let hint = match k {
+                        Tree::Str(s) => Some(s.as_str()),
+                        _ => hint,
+                    };
+                    count(k, hint, found) + count(v, hint, found)
+                })
+                .sum::<usize>()
+        }
+        _ => return 1,
+    };
+    let order = found.len();
+    found.entry(inline(tree))
+        .or_insert_with(|| Found {
+            count: 0,
+            size,
+            order,
+            hint: hint.map(str::to_owned),
+        })
+        .count += 1;
+    size
+}
+
+/// Replaces the sub-trees of the text by the name, keeping one.
+fn replace(tree: &mut Tree, text: &str, name: &str, kept: &mut Option<Tree>) {
+    let compound = match tree {
+        Tree::List(vs) => !vs.is_empty(),
+        Tree::Map(kvs) => !kvs.is_empty(),
+        _ => false,
+    };
+    if !compound {
+        return
+    }
+    if inline(tree) == text {
+        let tree = std::mem::replace(tree, Tree::Name(name.to_owned()));
+        kept.get_or_insert(tree);
+        return
+    }
+    match tree {
+        Tree::List(vs) => for v in vs.iter_mut() {
+            replace(v, text, name, kept);
+        },
+        Tree::Map(kvs) => for (k, v) in kvs.iter_mut() {
+            replace(k, text, name, kept);
+            replace(v, text, name, kept);
+        },
+        _ => (),
+    }
+}
+
+/// A name after the key, unlike the names taken.
+fn fresh(hint: Option<&str>, names: &mut HashSet<String>) -> String {
+    let mut name = String::new();
+    for c in hint.unwrap_or("shared").chars() {
+        if c.is_ascii_alphanumeric() {
+            name.push(c);
+        } else if !name.is_empty() && !name.ends_with('_') {
+            name.push('_');
+        }
+    }
+    let name = match name.trim_end_matches('_') {
+        "" => "shared".to_owned(),
+        s if s.starts_with(|c: char| c.is_ascii_digit()) => {
+            format!("key_{}", s)
+        }
+        s => s.to_owned(),
+    };
+    let mut fresh = name.clone();
+    for i in 2.. {
+        if names.insert(fresh.clone()) {
+            break
+        }
+        fresh = format!("{}_{}", name, i);
+    }
+    fresh
+}
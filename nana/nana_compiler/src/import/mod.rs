@@ -0,0 +1,315 @@
+//! Importing JSON, YAML and TOML documents as nana source.
+//!
+//! Objects, mappings and tables are maps, `{ "k": v }`, in the order
+//! written, and arrays are lists, `[ .. ]`. Nana has neither booleans nor
+//! nulls: `true` and `false` are `1` and `0`, as comparisons give, and
+//! `null` is `()`. TOML dates and times are strings. Integers are kept
+//! whole, up to 128 bits in YAML, whose parser reads larger ones as floats.
+//!
+//! When hoisting, a sub-tree found in several places is bound once in the
+//! binder space of the document, named after a key it's found at, and
+//! referred to by the name in each of them.
+
+mod construct;
+mod hoist;
+mod print;
+
+use crate::base::{Span, SrcFile};
+use num_bigint::BigInt;
+use std::fmt;
+use std::str::FromStr;
+
+/// The notation of a document imported.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Json,
+    Yaml,
+    Toml,
+}
+
+impl FromStr for Format {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(Format::Json),
+            "yaml" | "yml" => Ok(Format::Yaml),
+            "toml" => Ok(Format::Toml),
+            _ => Err(format!(
+                "expected `json`, `yaml` or `toml`, found `{}`", s
+            )),
+        }
+    }
+}
+
+impl fmt::Display for Format {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Format::Json => write!(f, "json"),
+            Format::Yaml => write!(f, "yaml"),
+            Format::Toml => write!(f, "toml"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Import {
+    /// Whether sub-trees found in several places are bound once.
+    pub hoist: bool,
+    /// The least size of a sub-tree hoisted, in keys and values.
+    pub size: usize,
+}
+
+impl Default for Import {
+    fn default() -> Self {
+        Self { hoist: false, size: 4 }
+    }
+}
+
+/// A document that doesn't parse in its notation.
+#[derive(Clone, Debug)]
+pub struct ImportError {
+    pub format: Format,
+    pub msg: String,
+    /// Where it fails in the document, if known.
+    pub span: Option<Span>,
+}
+
+/// A document as written in nana.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) enum Tree {
+    Unit,
+    Int(BigInt),
+    Float(f64),
+    Str(String),
+    List(Vec<Tree>),
+    Map(Vec<(Tree, Tree)>),
+    /// A hoisted sub-tree, by the name it's bound to.
+    Name(String),
+}
+
+pub fn import(
+    src: &SrcFile, format: Format, config: &Import
+) -> Result<String, ImportError> {
+    let text = src.text.as_str();
+    let error = |msg: String, offset: Option<usize>| {
+        let span = offset.map(|offset| {
+            let offset = floor(text, offset.min(text.len()));
+            let end = text[offset..].chars().next()
+                .map_or(offset, |c| offset + c.len_utf8());
+            src.span(offset, end)
+        });
+        ImportError { format, msg, span }
+    };
+    let mut tree = match format {
+        Format::Json => match serde_json::from_str::<serde_json::Value>(text) {
+            Ok(value) => Tree::from(value),
+            Err(err) => {
+                let offset = offset(text, err.line(), err.column());
+                return Err(error(message(&err), Some(offset)))
+            }
+        },
+        Format::Yaml => match serde_yaml::from_str::<Tree>(text) {
+            Ok(mut tree) => match construct::merge(&mut tree) {
+                Ok(()) => tree,
+                Err(msg) => return Err(error(msg.to_owned(), None)),
+            },
+            Err(err) => {
+                let offset = err.location().map(|loc| loc.index());
+                return Err(error(message(&err), offset))
+            }
+        },
+        Format::Toml => match text.parse::<toml::Table>() {
+            Ok(table) => Tree::from(toml::Value::Table(table)),
+            Err(err) => {
+                let offset = err.span().map(|span| span.start);
+                return Err(error(err.message().to_owned(), offset))
+            }
+        },
+    };
+    let bindings = match (config.hoist, &tree) {
+        (true, Tree::List(_) | Tree::Map(_)) => {
+            hoist::hoist(&mut tree, config.size)
+        }
+        _ => Vec::new(),
+    };
+    Ok(print::document(&tree, &bindings))
+}
+
+/// The message of an error, without its location.
+fn message(err: &dyn fmt::Display) -> String {
+    let msg = err.to_string();
+    match msg.find(" at line ") {
+        Some(i) => msg[..i].to_owned(),
+        None => msg,
+    }
+}
+
+/// The byte offset of the 1-based line and column.
+fn offset(text: &str, line: usize, col: usize) -> usize {
+    let start: usize = text.split_inclusive('\n')
+        .take(line.saturating_sub(1))
+        .map(str::len)
+        .sum();
+    let end = text[start..].find('\n').map_or(text.len(), |i| start + i);
+    (start + col.saturating_sub(1)).min(end)
+}
+
+/// The offset moved back to a char boundary.
+fn floor(text: &str, mut offset: usize) -> usize {
+    while !text.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    offset
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diagnostic::import::from_import_error;
+    use crate::emit::{json, yaml, Json, Yaml};
+    use crate::eval::eval;
+    use crate::external::parse;
+    use crate::Flatten;
+
+    fn import(text: &str, format: Format, config: &Import) -> String {
+        let src = SrcFile::new("doc", text);
+        match super::import(&src, format, config) {
+            Ok(out) => out,
+            Err(err) => from_import_error(err).render(&src),
+        }
+    }
+
+    fn eval_text(text: &str) -> crate::eval::Value {
+        let src = SrcFile::new("main.na", text);
+        let (nana, diags) = parse(&src);
+        assert!(diags.is_empty(), "{:?}", diags);
+        eval(nana.unwrap().flatten()).unwrap()
+    }
+
+    #[test]
+    fn json_documents() {
+        let text = r#"{
+            "name": "web", "debug": false, "ratio": -0.5, "none": null,
+            "ids": [1, 18446744073709551615,
+                340282366920938463463374607431768211455],
+            "empty": {}, "nil": [],
+            "note": "tab\there \"quoted\"",
+            "servers": [
+                { "host": "alpha.example.com", "port": 8080, "tls": true },
+                { "host": "beta.example.com", "port": 8081, "tls": true }
+            ]
+        }"#;
+        let out = import(text, Format::Json, &Import::default());
+        assert_eq!(out, r#"{
+    "name": "web",
+    "debug": 0,
+    "ratio": -0.5,
+    "none": (),
+    "ids": [1, 18446744073709551615, 340282366920938463463374607431768211455],
+    "empty": {},
+    "nil": [],
+    "note": "tab\there \"quoted\"",
+    "servers": [
+        { "host": "alpha.example.com", "port": 8080, "tls": 1 },
+        { "host": "beta.example.com", "port": 8081, "tls": 1 },
+    ],
+}"#);
+        let config = Json { pretty: false, ..Json::default() };
+        assert_eq!(json(&eval_text(&out), &config).unwrap(), "{\
+            \"name\":\"web\",\"debug\":0,\"ratio\":-0.5,\"none\":null,\
+            \"ids\":[1,18446744073709551615,\
+            340282366920938463463374607431768211455],\"empty\":{},\"nil\":[],\
+            \"note\":\"tab\\there \\\"quoted\\\"\",\"servers\":[\
+            {\"host\":\"alpha.example.com\",\"port\":8080,\"tls\":1},\
+            {\"host\":\"beta.example.com\",\"port\":8081,\"tls\":1}]}");
+    }
+
+    #[test]
+    fn hoisting() {
+        let text = "\
+defaults: &defaults
+  adapter: postgres
+  host: localhost
+  pool: [5, 10]
+development:
+  database: dev
+  <<: *defaults
+test:
+  database: test
+  <<: *defaults
+production: *defaults
+limits: [inf, .nan]
+ids:
+  - 340282366920938463463374607431768211455
+  - -170141183460469231731687303715884105728
+";
+        let config = Import { hoist: true, ..Import::default() };
+        let out = import(text, Format::Yaml, &config);
+        assert_eq!(out, r#"{
+    defaults = { "adapter": "postgres", "host": "localhost", "pool": [5, 10] };
+
+    "defaults": defaults,
+    "development": {
+        "database": "dev",
+        "adapter": "postgres",
+        "host": "localhost",
+        "pool": [5, 10],
+    },
+    "test": {
+        "database": "test",
+        "adapter": "postgres",
+        "host": "localhost",
+        "pool": [5, 10],
+    },
+    "production": defaults,
+    "limits": ["inf", (0.0 / 0.0)],
+    "ids": [
+        340282366920938463463374607431768211455,
+        -170141183460469231731687303715884105728,
+    ],
+}"#);
+        let out = yaml(&eval_text(&out), &Yaml::default()).unwrap();
+        assert!(out.starts_with("defaults: &defaults\n"));
+        assert!(out.contains("production: *defaults"));
+    }
+
+    #[test]
+    fn toml_documents() {
+        let text = r#"
+title = "app"
+tags = ["x", "y"]
+[owner]
+born = 1979-05-27T07:32:00Z
+[[plugins]]
+name = "a"
+opts = { level = 1, tags = ["x", "y"] }
+[[plugins]]
+name = "b"
+opts = { level = 1, tags = ["x", "y"] }
+"#;
+        let config = Import { hoist: true, size: 1 };
+        assert_eq!(import(text, Format::Toml, &config), r#"{
+    tags = ["x", "y"];
+    opts = { "level": 1, "tags": tags };
+
+    "title": "app",
+    "tags": tags,
+    "owner": { "born": "1979-05-27T07:32:00Z" },
+    "plugins": [{ "name": "a", "opts": opts }, { "name": "b", "opts": opts }],
+}"#);
+    }
+
+    #[test]
+    fn rejections() {
+        let text = "{\n  \"a\": [1, 2,]\n}";
+        assert_eq!(import(text, Format::Json, &Import::default()), "\
+error: invalid json
+ --> doc:2:14
+  |
+2 |   \"a\": [1, 2,]
+  |              ^ trailing comma
+");
+        assert!(import("a = ", Format::Toml, &Import::default())
+            .starts_with("error: invalid toml\n --> doc:1:5"));
+    }
+}
@@ -0,0 +1,127 @@
+//! Printing trees as nana source.
+
+use super::Tree;
+use std::fmt::Write;
+
+/// The widest a line is written, unless a value in it can't be broken.
+const WIDTH: usize = 80;
+const INDENT: &str = "    ";
+
+/// The source of the tree, after the bindings of its binder space.
+pub(super) fn document(tree: &Tree, bindings: &[(String, Tree)]) -> String {
+    let mut out = String::new();
+    if bindings.is_empty() {
+        node(&mut out, tree, 0, 0);
+        return out
+    }
+    let (open, close) = match tree {
+        Tree::List(_) => ("[", "]"),
+        _ => ("{", "}"),
+    };
+    out += open;
+    out += "\n";
+    for (name, tree) in bindings.iter() {
+        let _ = write!(out, "{}{} = ", INDENT, name);
+        let used = out.len() - out.rfind('\n').map_or(0, |i| i + 1) + 1;
+        node(&mut out, tree, 1, used);
+        out += ";\n";
+    }
+    out += "\n";
+    entries(&mut out, tree, 1);
+    out += close;
+    out
+}
+
+/// Writes the tree in a line if it fits after the columns used, and its
+/// entries a line each otherwise.
+fn node(out: &mut String, tree: &Tree, depth: usize, used: usize) {
+    let line = inline(tree);
+    let broken = match tree {
+        Tree::List(vs) => !vs.is_empty(),
+        Tree::Map(kvs) => !kvs.is_empty(),
+        _ => false,
+    };
+    if !broken || used + line.len() <= WIDTH {
+        *out += &line;
+        return
+    }
+    let (open, close) = match tree {
+        Tree::List(_) => ("[", "]"),
+        _ => ("{", "}"),
+    };
+    *out += open;
+    *out += "\n";
+    entries(out, tree, depth + 1);
+    *out += &INDENT.repeat(depth);
+    *out += close;
+}
+
+/// Writes the entries of a list or a map a line each, indented.
+fn entries(out: &mut String, tree: &Tree, depth: usize) {
+    let indent = INDENT.repeat(depth);
+    match tree {
+        Tree::List(vs) => for v in vs.iter() {
+            *out += &indent;
+            node(out, v, depth, indent.len() + 1);
+            *out += ",\n";
+        },
+        Tree::Map(kvs) => for (k, v) in kvs.iter() {
+            let key = inline(k);
+            let _ = write!(out, "{}{}: ", indent, key);
+            node(out, v, depth, indent.len() + key.len() + 3);
+            *out += ",\n";
+        },
+        tree => {
+            *out += &indent;
+            *out += &inline(tree);
+            *out += "\n";
+        }
+    }
+}
+
+/// The tree in a line.
+pub(super) fn inline(tree: &Tree) -> String {
+    match tree {
+        Tree::Unit => "()".to_owned(),
+        Tree::Int(i) => i.to_string(),
+        Tree::Float(x) if x.is_nan() => "(0.0 / 0.0)".to_owned(),
+        Tree::Float(x) if x.is_infinite() => {
+            if *x > 0.0 { "(1.0 / 0.0)" } else { "(-1.0 / 0.0)" }.to_owned()
+        }
+        Tree::Float(x) => format!("{:?}", x),
+        Tree::Str(s) => quote(s),
+        Tree::List(vs) => {
+            let vs: Vec<_> = vs.iter().map(inline).collect();
+            format!("[{}]", vs.join(", "))
+        }
+        Tree::Map(kvs) if kvs.is_empty() => "{}".to_owned(),
+        Tree::Map(kvs) => {
+            let kvs: Vec<_> = kvs.iter()
+                .map(|(k, v)| format!("{}: {}", inline(k), inline(v)))
+                .collect();
+            format!("{{ {} }}", kvs.join(", "))
+        }
+        Tree::Name(name) => name.clone(),
+    }
+}
+
+/// The string quoted and escaped as a nana literal.
+fn quote(s: &str) -> String {
+    let mut out = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\r' => out += "\\r",
+            '\t' => out += "\\t",
+            '\0' => out += "\\0",
+            c if c.is_control() => {
+                let _ = write!(out, "\\u{{{:x}}}", c as u32);
+            }
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,121 @@
+//! Implicit binding guards.
+//!
+//! As in lala, a binding is led by a guard `~`, so that the parser tells the
+//! pattern of a binding from an expression without unbounded lookahead.
+//! Unlike lala, nana doesn't require the guard to be written: `Guard` looks
+//! ahead at the start of every item of a block, and inserts one if the item
+//! turns out to be a binding, i.e. a `=` or `:=` shows up before it ends.
+
+use super::{LexError, Token};
+use crate::base::*;
+use std::collections::VecDeque;
+
+type Item = Result<(Loc, Token, Loc), LexError>;
+
+pub struct Guard<I> {
+    tokens: I,
+    buffer: VecDeque<Item>,
+    /// Whether the next token starts an item of a block.
+    start: bool,
+    /// Number of tokens left in the pattern of a guarded binding,
+    /// within which no item starts.
+    pattern: usize,
+}
+
+impl<I> Guard<I>
+where I: Iterator<Item = Item> {
+    pub fn new(tokens: I) -> Self {
+        Self { tokens, buffer: VecDeque::new(), start: true, pattern: 0 }
+    }
+
+    fn peek(&mut self, n: usize) -> Option<&Item> {
+        while self.buffer.len() <= n {
+            let item = self.tokens.next()?;
+            self.buffer.push_back(item);
+        }
+        self.buffer.get(n)
+    }
+
+    /// Finds the bridge `=` or `:=` of the item from the `n`th token on,
+    /// returning its index.
+    fn bridge(&mut self, mut n: usize) -> Option<usize> {
+        let mut depth = 0;
+        // exposure patterns `<a; b>` are delimited by angles
+        let mut angle = 0;
+        loop {
+            let tok = match self.peek(n)? {
+                Ok((_, tok, _)) => tok,
+                Err(_) => return None,
+            };
+            match tok {
+                Token::LParen | Token::LBracket | Token::LBrace => depth += 1,
+                Token::RParen | Token::RBracket | Token::RBrace => {
+                    if depth == 0 {
+                        return None
+                    }
+                    depth -= 1;
+                }
+                Token::Lt => angle += 1,
+                Token::Gt if angle > 0 => angle -= 1,
+                Token::Semi | Token::Comma if depth == 0 && angle == 0 => {
+                    return None
+                }
+                Token::Eq | Token::ColonEq if depth == 0 => return Some(n),
+                _ => (),
+            }
+            n += 1;
+        }
+    }
+}
+
+impl<I> Iterator for Guard<I>
+where I: Iterator<Item = Item> {
+    type Item = Item;
+
+    fn next(&mut self) -> Option<Item> {
+        if self.start && self.pattern == 0 {
+            let (loc, guarded) = match self.peek(0) {
+                Some(Ok((loc, tok, _))) => (*loc, *tok == Token::Tilde),
+                _ => (Loc::default(), false),
+            };
+            if guarded {
+                self.pattern = self.bridge(1).map_or(0, |n| n + 1);
+            } else if let Some(n) = self.bridge(0) {
+                self.start = false;
+                self.pattern = n + 1;
+                return Some(Ok((loc, Token::Tilde, loc)))
+            }
+        }
+        let item = match self.buffer.pop_front() {
+            Some(item) => item,
+            None => self.tokens.next()?,
+        };
+        self.pattern = self.pattern.saturating_sub(1);
+        self.start = matches!(&item, Ok((_,
+            Token::LParen | Token::LBracket | Token::LBrace | Token::Semi,
+        _)));
+        Some(item)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::Lexer;
+
+    fn guarded(text: &str) -> String {
+        Guard::new(Lexer::new(text))
+            .map(|t| t.unwrap().1.to_string())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    #[test]
+    fn guarding() {
+        assert_eq!(
+            guarded("<a; b> = x; ~ (c = d) := [e := f; e]; (g, h)"),
+            "~ < a ; b > = x ; ~ ( c = d ) := [ ~ e := f ; e ] ; ( g , h )"
+        );
+        assert_eq!(guarded("{ k: v, w }"), "{ k : v , w }");
+    }
+}
@@ -1,10 +1,17 @@
-mod base;
-mod external;
-mod flatten;
-// mod resolve;
+pub mod base;
+pub mod depend;
+pub mod diagnostic;
+pub mod emit;
+pub mod eval;
+pub mod external;
+pub mod flatten;
+pub mod import;
+pub mod lexer;
+pub mod resolve;
+pub mod visible;
 use external::ast as nana_ast;
 
 #[macro_use] extern crate lalrpop_util;
-lalrpop_mod!(pub nana);
+lalrpop_mod!(#[allow(clippy::all)] pub nana);
 
 pub use flatten::Flatten;
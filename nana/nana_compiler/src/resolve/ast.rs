@@ -0,0 +1,155 @@
+//! In resolved Ast every `Binder` is either a definition or a use of one,
+//! identified by a unique `DefId`.
+
+pub use crate::base::*;
+use std::fmt;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DefId(pub usize);
+
+/// Where a binder is defined.
+#[derive(Clone)]
+pub struct Def {
+    pub id: DefId,
+    pub binder: Binder,
+}
+
+/// Where a binder is referred to.
+#[derive(Clone)]
+pub struct Use {
+    pub def: DefId,
+    pub binder: Binder,
+}
+
+#[derive(Clone, Debug)]
+pub struct Nana {
+    pub body: Spanned<GatedBlock>,
+    /// All definitions indexed by `DefId`.
+    pub defs: Vec<Spanned<Binder>>,
+    /// The definitions of the builtin operators.
+    pub builtins: Vec<DefId>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GatedBlock {
+    pub traces: Vec<Spanned<Def>>,
+    pub block: Spanned<Block>,
+    /// Binders free in the block, captured from outer scopes in the order
+    /// first referred to; builtins are never captured.
+    pub captures: Vec<Capture>,
+}
+
+/// A binder referred to by a gated block but defined outside of it.
+#[derive(Clone, Debug)]
+pub struct Capture {
+    pub def: DefId,
+    pub binder: Binder,
+    /// Where it's first referred to within the gated block.
+    pub span: Span,
+    /// The block or gate defining it.
+    pub scope: Span,
+}
+
+#[derive(Clone, Debug)]
+pub enum Block {
+    Tuple(Vec<Spanned<Abstraction>>, Vec<Spanned<Expr>>),
+    List(Vec<Spanned<Abstraction>>, Vec<Spanned<Expr>>),
+    Set(Vec<Spanned<Abstraction>>, Vec<Spanned<Expr>>),
+    Map(Vec<Spanned<Abstraction>>, Vec<Spanned<Pair>>),
+}
+
+/// The binder space and the value space of a block, where a map block has
+/// pairs as values and others have exprs.
+pub type Spaces<'a> = (
+    &'a [Spanned<Abstraction>],
+    &'a [Spanned<Expr>],
+    &'a [Spanned<Pair>],
+);
+
+impl Block {
+    pub fn spaces(&self) -> Spaces<'_> {
+        match self {
+            Block::Tuple(bds, vls)
+            | Block::List(bds, vls)
+            | Block::Set(bds, vls) => (bds, vls, &[]),
+            Block::Map(bds, pairs) => (bds, &[], pairs),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Abstraction {
+    pub trace: Spanned<Pattern>,
+    pub exposed: bool,
+    /// Shared with the closures evaluated from it.
+    pub src: Spanned<Arc<GatedBlock>>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Pattern {
+    Alias(Box<Spanned<Pattern>>, Box<Spanned<Pattern>>),
+    Wild,
+    Forall,
+    Literal(Literal),
+    Binder(Def),
+    Exposure(Vec<Spanned<ExposurePattern>>),
+    Tuple(Vec<Spanned<Pattern>>),
+    List(Vec<Spanned<Pattern>>),
+    Map(Vec<Spanned<PairPattern>>),
+}
+
+#[derive(Clone, Debug)]
+pub enum ExposurePattern {
+    Binder(Def),
+    /// `<..>` defines whatever exposed, thus any name not found otherwise.
+    All(Def),
+}
+
+#[derive(Clone, Debug)]
+pub struct PairPattern {
+    pub key: Spanned<Expr>,
+    pub val: Spanned<Pattern>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Pair {
+    pub key: Spanned<Expr>,
+    pub val: Spanned<Expr>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Branch {
+    pub pattern: Spanned<Pattern>,
+    pub body: Spanned<Expr>,
+}
+
+#[derive(Clone, Debug)]
+pub enum Expr {
+    Literal(Literal),
+    Binder(Use),
+    /// Shared with the closures evaluated from it.
+    GatedBlock(Arc<GatedBlock>),
+    Application(Box<Spanned<Expr>>, Box<Spanned<Expr>>),
+    /// The projected binder is left to the visibility check.
+    Projection(Box<Spanned<Expr>>, Spanned<Binder>),
+    Matching(Box<Spanned<Expr>>, Vec<Spanned<Branch>>),
+}
+
+impl fmt::Debug for DefId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "#{}", self.0)
+    }
+}
+
+impl fmt::Debug for Def {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}{:?}", self.binder, self.id)
+    }
+}
+
+impl fmt::Debug for Use {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}{:?}", self.binder, self.def)
+    }
+}
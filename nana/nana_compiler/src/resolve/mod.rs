@@ -1,2 +1,5 @@
 pub mod ast;
-pub mod resolve;
\ No newline at end of file
+#[allow(clippy::module_inception)]
+pub mod resolve;
+
+pub use resolve::resolve;
\ No newline at end of file
@@ -1,40 +1,691 @@
-use crate::nana_ast::*;
+//! Resolves binders by the rules of the three blocks:
+//!
+//! - `()` bindings can't refer to each other, nor shadow each other;
+//! - `[]` bindings are sequential, each may refer to or shadow the former;
+//! - `{}` bindings may refer to each other in any order, but can't collide.
+//!
+//! Values of a block always see all its bindings.
 
-#[derive(Clone)]
-pub struct Ctx {
-    inheritted: Option<Box<Ctx>>,
-    introduced: Pattern,
-    evaluated: Vec<(Pattern, Expr)>,
+use super::ast as ra;
+use crate::diagnostic::{resolve::from_resolve_error, Diagnostic};
+use crate::flatten::ast as fa;
+use crate::base::*;
+use crate::lexer::lex::OPERATORS;
+use ra::DefId;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockKind {
+    Tuple,
+    List,
+    Set,
+    Map,
+}
+
+#[derive(Clone, Debug)]
+pub enum ResolveError {
+    Unbound {
+        name: String,
+        span: Span,
+    },
+    /// A `()` binding refers to a sibling.
+    Overlap {
+        name: String,
+        span: Span,
+        sibling: Span,
+    },
+    /// A binder is bound twice where it's not allowed.
+    Rebound {
+        name: String,
+        span: Span,
+        prev: Span,
+        /// `None` if bound twice by the same pattern or gate.
+        block: Option<BlockKind>,
+    },
+    /// A `[]` binding refers to itself or a later sibling.
+    Early {
+        name: String,
+        span: Span,
+        later: Span,
+        /// Whether referred to by a gated block.
+        captured: bool,
+    },
+    /// An expr recovered from a syntax error.
+    Erroneous {
+        span: Span,
+    },
+}
+
+/// Resolves the flattened ast; the names of all operators are builtin.
+pub fn resolve(nana: fa::Nana) -> Result<ra::Nana, Vec<Diagnostic>> {
+    let mut resolver = Resolver::new();
+    let body = resolver.gated_block(nana.body);
+    if resolver.errors.is_empty() {
+        let Resolver { defs, builtins, .. } = resolver;
+        Ok(ra::Nana { body, defs, builtins })
+    } else {
+        Err(resolver.errors.into_iter().map(from_resolve_error).collect())
+    }
+}
+
+#[derive(Default)]
+struct Scope {
+    /// The block or gate of the scope.
+    span: Span,
+    names: HashMap<String, DefId>,
+    /// Set by an exposure pattern `<..>`.
+    open: Option<DefId>,
+    /// Names known to be exposed by `<..>`, which is their def.
+    exposed: HashMap<String, DefId>,
+    /// Siblings of the `()` bindings being resolved.
+    hidden: bool,
+    /// Binders of the `[]` binding being resolved and those after it.
+    later: HashMap<String, Span>,
+}
+
+impl Scope {
+    fn new(span: Span) -> Self {
+        Self { span, ..Self::default() }
+    }
+}
+
+/// A gated block being resolved.
+struct Gate {
+    /// Index of the scope of its traces.
+    depth: usize,
+    /// Whether it has any trace, thus evaluated only when applied.
+    closure: bool,
+    captures: Vec<ra::Capture>,
+}
+
+struct Resolver {
+    defs: Vec<Spanned<Binder>>,
+    builtins: Vec<DefId>,
+    scopes: Vec<Scope>,
+    gates: Vec<Gate>,
+    errors: Vec<ResolveError>,
+    /// Names exposed by the blocks bound to binders, when known.
+    exposes: HashMap<DefId, Vec<String>>,
 }
 
-#[derive(Clone)]
-pub struct Contexted<T> (Ctx, T);
+/// Binders bound by patterns in order, along with `<..>` if any.
+#[derive(Default)]
+struct Bound {
+    names: Vec<(String, DefId)>,
+    open: Option<DefId>,
+    /// Names known to be exposed by `<..>`.
+    exposed: Vec<String>,
+}
+
+impl Resolver {
+    fn new() -> Self {
+        let mut resolver = Self {
+            defs: Vec::new(),
+            builtins: Vec::new(),
+            scopes: Vec::new(),
+            gates: Vec::new(),
+            errors: Vec::new(),
+            exposes: HashMap::new(),
+        };
+        let mut builtins = Scope::default();
+        for op in OPERATORS.iter().chain(&["<", ">"]) {
+            let binder = Node::new(Binder::from(*op), Span::default());
+            let def = resolver.define(binder);
+            builtins.names.insert(op.to_string(), def.id);
+            resolver.builtins.push(def.id);
+        }
+        resolver.scopes.push(builtins);
+        resolver
+    }
+
+    fn define(&mut self, binder: Spanned<Binder>) -> ra::Def {
+        let id = DefId(self.defs.len());
+        self.defs.push(binder.clone());
+        ra::Def { id, binder: binder.inner() }
+    }
+
+    /// The def of the name in scope, and the depth of its scope; `<..>`
+    /// of unknown blocks are tried only if bound nowhere else.
+    fn find(&self, name: &str) -> Option<(usize, DefId)> {
+        let visible = || self.scopes.iter().enumerate().rev()
+            .filter(|(_, s)| !s.hidden);
+        visible()
+            .find_map(|(i, s)| {
+                let def = s.names.get(name).or_else(|| s.exposed.get(name));
+                Some((i, *def?))
+            })
+            .or_else(|| visible().find_map(|(i, s)| Some((i, s.open?))))
+    }
+
+    fn lookup(&mut self, binder: Spanned<Binder>) -> ra::Use {
+        let name = binder.inner_ref().as_str();
+        let found = self.find(name);
+        let def = match found {
+            Some((depth, def)) => {
+                self.capture(depth, def, &binder);
+                def
+            }
+            None => {
+                let sibling = self.scopes.iter().rev()
+                    .filter(|s| s.hidden)
+                    .find_map(|s| s.names.get(name));
+                let later = self.scopes.iter().enumerate().rev()
+                    .find_map(|(i, s)| Some((i, *s.later.get(name)?)));
+                let name = name.to_owned();
+                let span = binder.span();
+                self.errors.push(match (sibling, later) {
+                    (Some(def), _) => {
+                        let sibling = self.defs[def.0].span();
+                        ResolveError::Overlap { name, span, sibling }
+                    }
+                    (None, Some((depth, later))) => {
+                        let captured = self.gates.iter()
+                            .any(|g| g.closure && g.depth > depth);
+                        ResolveError::Early { name, span, later, captured }
+                    }
+                    (None, None) => ResolveError::Unbound { name, span },
+                });
+                self.define(binder.clone()).id
+            }
+        };
+        ra::Use { def, binder: binder.inner() }
+    }
+
+    /// Records the def found in the scope at `depth` as captured by every
+    /// gated block within that scope.
+    fn capture(&mut self, depth: usize, def: DefId, binder: &Spanned<Binder>) {
+        // builtins
+        if depth == 0 {
+            return
+        }
+        let scope = self.scopes[depth].span;
+        for gate in self.gates.iter_mut().filter(|g| g.depth > depth) {
+            if gate.captures.iter().all(|c| c.def != def) {
+                gate.captures.push(ra::Capture {
+                    def,
+                    binder: binder.inner_ref().clone(),
+                    span: binder.span(),
+                    scope,
+                });
+            }
+        }
+    }
+
+    /// Brings the bound binders into the innermost scope; a binder already
+    /// there is reported as rebound unless in a list block.
+    fn bind(&mut self, bound: Bound, block: Option<BlockKind>) {
+        if let Some(open) = bound.open {
+            let scope = self.scopes.last_mut().expect("no scope");
+            scope.open = Some(open);
+            for name in bound.exposed {
+                scope.exposed.insert(name, open);
+            }
+        }
+        for (name, def) in bound.names {
+            let scope = self.scopes.last_mut().expect("no scope");
+            let prev = scope.names.insert(name.clone(), def);
+            match (prev, block) {
+                (_, Some(BlockKind::List)) | (None, _) => (),
+                (Some(prev), _) => {
+                    let span = self.defs[def.0].span();
+                    let prev = self.defs[prev.0].span();
+                    self.errors.push(
+                        ResolveError::Rebound { name, span, prev, block }
+                    );
+                }
+            }
+        }
+    }
+
+    fn gated_block(
+        &mut self, g: Spanned<fa::GatedBlock>
+    ) -> Spanned<ra::GatedBlock> {
+        let span = g.span();
+        g.map(|g| {
+            let depth = self.scopes.len();
+            let closure = !g.traces.is_empty();
+            self.gates.push(Gate { depth, closure, captures: Vec::new() });
+            self.scopes.push(Scope::new(span));
+            let mut bound = Bound::default();
+            let traces = g.traces.into_iter().map(|trace| {
+                let span = trace.span();
+                Node::new(self.define_bound(trace, &mut bound), span)
+            }).collect();
+            self.bind(bound, None);
+            let block = self.block(g.block);
+            self.scopes.pop();
+            let captures = self.gates.pop().expect("no gate").captures;
+            ra::GatedBlock { traces, block, captures }
+        })
+    }
+
+    fn block(&mut self, block: Spanned<fa::Block>) -> Spanned<ra::Block> {
+        let span = block.span();
+        block.map(|block| {
+            let block = match block {
+                fa::Block::Tuple(bds, vls) => {
+                    let bds = self.binder_space(BlockKind::Tuple, span, bds);
+                    ra::Block::Tuple(bds, self.value_space(vls))
+                }
+                fa::Block::List(bds, vls) => {
+                    let bds = self.binder_space(BlockKind::List, span, bds);
+                    ra::Block::List(bds, self.value_space(vls))
+                }
+                fa::Block::Set(bds, vls) => {
+                    let bds = self.binder_space(BlockKind::Set, span, bds);
+                    ra::Block::Set(bds, self.value_space(vls))
+                }
+                fa::Block::Map(bds, vls) => {
+                    let bds = self.binder_space(BlockKind::Map, span, bds);
+                    let vls = vls.into_iter().map(|pair| pair.map(|pair| {
+                        ra::Pair {
+                            key: self.expr(pair.key),
+                            val: self.expr(pair.val),
+                        }
+                    })).collect();
+                    ra::Block::Map(bds, vls)
+                }
+            };
+            // pushed by binder space
+            self.scopes.pop();
+            block
+        })
+    }
+
+    /// Resolves the bindings, leaving a scope of them for the value space.
+    fn binder_space(
+        &mut self,
+        kind: BlockKind,
+        span: Span,
+        bds: Vec<Spanned<fa::Abstraction>>,
+    ) -> Vec<Spanned<ra::Abstraction>> {
+        if kind == BlockKind::List {
+            self.scopes.push(Scope::new(span));
+            let names: Vec<_> = bds.iter().map(|abs| {
+                let mut names = Vec::new();
+                pattern_names(&abs.inner_ref().trace, &mut names);
+                names
+            }).collect();
+            let bds = bds.into_iter().enumerate().map(|(i, abs)| {
+                abs.map(|abs| {
+                    // the earliest binder of each name from here on
+                    let later = names[i..].iter().flatten().rev().cloned();
+                    self.scopes.last_mut().expect("no scope").later =
+                        later.collect();
+                    let known = self.exposed_by(abs.src.inner_ref());
+                    let src = self.gated_block(abs.src).map(Arc::new);
+                    let mut bound = Bound::default();
+                    let trace = self.pattern(abs.trace, &mut bound);
+                    self.known(&trace, &mut bound, known);
+                    self.bind(bound, Some(kind));
+                    ra::Abstraction { trace, exposed: abs.exposed, src }
+                })
+            }).collect();
+            self.scopes.last_mut().expect("no scope").later.clear();
+            return bds
+        }
+        // patterns first, then the sources
+        let mut bounds = Vec::new();
+        let heads: Vec<_> = bds.into_iter().map(|abs| {
+            let span = abs.span();
+            let abs = abs.inner();
+            let mut bound = Bound::default();
+            let trace = self.pattern(abs.trace, &mut bound);
+            bounds.push(bound);
+            (span, trace, abs.exposed, abs.src)
+        }).collect();
+        self.scopes.push(Scope {
+            hidden: kind == BlockKind::Tuple,
+            ..Scope::new(span)
+        });
+        // the blocks of siblings first, which `<..>` may expose
+        for (_, trace, _, src) in heads.iter() {
+            if let ra::Pattern::Binder(def) = trace.inner_ref() {
+                if let Some(names) = self.exposed_by(src.inner_ref()) {
+                    self.exposes.insert(def.id, names);
+                }
+            }
+        }
+        for (mut bound, (_, trace, _, src)) in bounds.into_iter().zip(&heads) {
+            let known = self.exposed_by(src.inner_ref());
+            self.known(trace, &mut bound, known);
+            self.bind(bound, Some(kind));
+        }
+        let bds = heads.into_iter().map(|(span, trace, exposed, src)| {
+            let src = self.gated_block(src).map(Arc::new);
+            Node::new(ra::Abstraction { trace, exposed, src }, span)
+        }).collect();
+        self.scopes.last_mut().expect("no scope").hidden = false;
+        bds
+    }
+
+    /// Records the names known to be exposed by the source of the trace.
+    fn known(
+        &mut self,
+        trace: &Spanned<ra::Pattern>,
+        bound: &mut Bound,
+        known: Option<Vec<String>>,
+    ) {
+        let known = match known {
+            Some(known) => known,
+            None => return,
+        };
+        if bound.open.is_some() {
+            bound.exposed = known.clone();
+        }
+        if let ra::Pattern::Binder(def) = trace.inner_ref() {
+            self.exposes.insert(def.id, known);
+        }
+    }
+
+    /// The names exposed by the block the gated block evaluates to, if
+    /// known before evaluation: written in place or bound to a binder,
+    /// and exposing no `<..>` in turn.
+    fn exposed_by(&self, g: &fa::GatedBlock) -> Option<Vec<String>> {
+        if !g.traces.is_empty() {
+            return None
+        }
+        let bds = match g.block.inner_ref() {
+            fa::Block::Tuple(bds, vls) if bds.is_empty() && vls.len() == 1 => {
+                return match vls[0].inner_ref() {
+                    fa::Expr::Binder(b) => {
+                        let (_, def) = self.find(b.as_str())?;
+                        self.exposes.get(&def).cloned()
+                    }
+                    fa::Expr::GatedBlock(g) => self.exposed_by(g),
+                    _ => None,
+                }
+            }
+            fa::Block::Tuple(bds, _) | fa::Block::List(bds, _) |
+            fa::Block::Set(bds, _) | fa::Block::Map(bds, _) => bds,
+        };
+        let mut names = Vec::new();
+        for abs in bds.iter().filter(|abs| abs.inner_ref().exposed) {
+            let trace = &abs.inner_ref().trace;
+            if opens(trace.inner_ref()) {
+                return None
+            }
+            pattern_names(trace, &mut names);
+        }
+        Some(names.into_iter().map(|(name, _)| name).collect())
+    }
 
-impl<T> Inner for Contexted<T> {
-    type Target = T;
-    fn inner(self) -> Self::Target {
-        self.1
+    fn value_space(
+        &mut self, vls: Vec<Spanned<fa::Expr>>
+    ) -> Vec<Spanned<ra::Expr>> {
+        vls.into_iter().map(|e| self.expr(e)).collect()
     }
-    fn inner_ref(&self) -> &Self::Target {
-        &self.1
+
+    /// Defines a binder into `bound`, which can't be bound twice.
+    fn define_bound(
+        &mut self, binder: Spanned<Binder>, bound: &mut Bound
+    ) -> ra::Def {
+        let span = binder.span();
+        let def = self.define(binder);
+        let name = def.binder.as_str().to_owned();
+        match bound.names.iter().find(|(n, _)| *n == name) {
+            Some((_, prev)) => {
+                let prev = self.defs[prev.0].span();
+                let block = None;
+                self.errors.push(
+                    ResolveError::Rebound { name, span, prev, block }
+                );
+            }
+            None => bound.names.push((name, def.id)),
+        }
+        def
+    }
+
+    /// Defines the binders of the pattern into `bound`, not yet in scope.
+    fn pattern(
+        &mut self, pat: Spanned<fa::Pattern>, bound: &mut Bound
+    ) -> Spanned<ra::Pattern> {
+        let span = pat.span();
+        let pat = match pat.inner() {
+            fa::Pattern::Alias(al, p) => {
+                let al = self.pattern(*al, bound);
+                let p = self.pattern(*p, bound);
+                ra::Pattern::Alias(Box::new(al), Box::new(p))
+            }
+            fa::Pattern::Wild => ra::Pattern::Wild,
+            fa::Pattern::Forall => ra::Pattern::Forall,
+            fa::Pattern::Literal(l) => ra::Pattern::Literal(l),
+            fa::Pattern::Binder(b) => {
+                ra::Pattern::Binder(self.define_bound(Node::new(b, span), bound))
+            }
+            fa::Pattern::Exposure(ex) => {
+                ra::Pattern::Exposure(ex.into_iter().map(|ex| {
+                    let span = ex.span();
+                    let ex = match ex.inner() {
+                        fa::ExposurePattern::Binder(b) => {
+                            let b = Node::new(b, span);
+                            ra::ExposurePattern::Binder(
+                                self.define_bound(b, bound)
+                            )
+                        }
+                        fa::ExposurePattern::All => {
+                            let def = self.define(
+                                Node::new(Binder::from(".."), span)
+                            );
+                            bound.open = Some(def.id);
+                            ra::ExposurePattern::All(def)
+                        }
+                    };
+                    Node::new(ex, span)
+                }).collect())
+            }
+            fa::Pattern::Tuple(ps) => ra::Pattern::Tuple(
+                ps.into_iter().map(|p| self.pattern(p, bound)).collect()
+            ),
+            fa::Pattern::List(ps) => ra::Pattern::List(
+                ps.into_iter().map(|p| self.pattern(p, bound)).collect()
+            ),
+            fa::Pattern::Map(ps) => ra::Pattern::Map(
+                ps.into_iter().map(|p| p.map(|p| ra::PairPattern {
+                    key: self.expr(p.key),
+                    val: self.pattern(p.val, bound),
+                })).collect()
+            ),
+        };
+        Node::new(pat, span)
+    }
+
+    fn expr(&mut self, e: Spanned<fa::Expr>) -> Spanned<ra::Expr> {
+        let span = e.span();
+        let e = match e.inner() {
+            fa::Expr::Literal(l) => ra::Expr::Literal(l),
+            fa::Expr::Binder(b) => {
+                ra::Expr::Binder(self.lookup(Node::new(b, span)))
+            }
+            fa::Expr::GatedBlock(g) => {
+                let g = self.gated_block(Node::new(g, span));
+                ra::Expr::GatedBlock(Arc::new(g.inner()))
+            }
+            fa::Expr::Application(f, a) => ra::Expr::Application(
+                Box::new(self.expr(*f)),
+                Box::new(self.expr(*a)),
+            ),
+            fa::Expr::Projection(e, b) => {
+                ra::Expr::Projection(Box::new(self.expr(*e)), b)
+            }
+            fa::Expr::Matching(e, bs) => {
+                let e = self.expr(*e);
+                let bs = bs.into_iter().map(|b| {
+                    let span = b.span();
+                    b.map(|b| {
+                        let mut bound = Bound::default();
+                        let pattern = self.pattern(b.pattern, &mut bound);
+                        self.scopes.push(Scope::new(span));
+                        self.bind(bound, None);
+                        let body = self.expr(b.body);
+                        self.scopes.pop();
+                        ra::Branch { pattern, body }
+                    })
+                }).collect();
+                ra::Expr::Matching(Box::new(e), bs)
+            }
+            fa::Expr::Error => {
+                self.errors.push(ResolveError::Erroneous { span });
+                // never seen, since resolving fails
+                ra::Expr::Literal(Literal::Int(0.into()))
+            }
+        };
+        Node::new(e, span)
+    }
+}
+
+/// Whether the pattern has a `<..>`.
+fn opens(pat: &fa::Pattern) -> bool {
+    match pat {
+        fa::Pattern::Alias(al, p) => {
+            opens(al.inner_ref()) || opens(p.inner_ref())
+        }
+        fa::Pattern::Exposure(ex) => ex.iter()
+            .any(|ex| matches!(ex.inner_ref(), fa::ExposurePattern::All)),
+        fa::Pattern::Tuple(ps) | fa::Pattern::List(ps) => {
+            ps.iter().any(|p| opens(p.inner_ref()))
+        }
+        fa::Pattern::Map(ps) => {
+            ps.iter().any(|p| opens(p.inner_ref().val.inner_ref()))
+        }
+        _ => false,
     }
-    fn inner_mut(&mut self) -> &mut Self::Target {
-        &mut self.1
+}
+
+/// Collects the binders of the pattern, the keys of maps excluded.
+fn pattern_names(
+    pat: &Spanned<fa::Pattern>, names: &mut Vec<(String, Span)>
+) {
+    match pat.inner_ref() {
+        fa::Pattern::Alias(al, p) => {
+            pattern_names(al, names);
+            pattern_names(p, names);
+        }
+        fa::Pattern::Binder(b) => {
+            names.push((b.as_str().to_owned(), pat.span()))
+        }
+        fa::Pattern::Exposure(ex) => {
+            for e in ex.iter() {
+                if let fa::ExposurePattern::Binder(b) = e.inner_ref() {
+                    names.push((b.as_str().to_owned(), e.span()));
+                }
+            }
+        }
+        fa::Pattern::Tuple(ps) | fa::Pattern::List(ps) => {
+            for p in ps.iter() {
+                pattern_names(p, names);
+            }
+        }
+        fa::Pattern::Map(ps) => {
+            for p in ps.iter() {
+                pattern_names(&p.inner_ref().val, names);
+            }
+        }
+        _ => (),
     }
 }
 
-// use std::ops::{Deref, DerefMut};
-// impl<T> Deref for Contexted<T> {
-//     type Target = T;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external::parse;
+    use crate::Flatten;
+
+    /// The messages and lines of the errors reported.
+    fn errors(text: &str) -> Vec<(String, usize)> {
+        let src = SrcFile::new("main.na", text);
+        let (nana, diags) = parse(&src);
+        assert!(diags.is_empty());
+        match resolve(nana.unwrap().flatten()) {
+            Ok(_) => Vec::new(),
+            Err(diags) => diags
+                .into_iter()
+                .map(|d| (d.msg.clone(), d.span().unwrap().start.line))
+                .collect(),
+        }
+    }
 
-//     fn deref(&self) -> &Self::Target {
-//         &self.1
-//     }
-// }
-// impl<T> DerefMut for Contexted<T> {
-//     fn deref_mut(&mut self) -> &mut Self::Target {
-//         &mut self.1
-//     }
-// }
+    #[test]
+    fn tuples() {
+        assert_eq!(errors("x = 1;\n( x = x; y = x; (x, y) )"), vec![]);
+        assert_eq!(errors("(\n  a = 1;\n  b = a;\n  a = 2;\n)"), vec![
+            ("`a` is bound more than once in a tuple block".into(), 4),
+            ("`a` is bound by a sibling in a tuple block".into(), 3),
+        ]);
+    }
 
+    #[test]
+    fn lists() {
+        assert_eq!(errors("[ one = 0; one = 1; fin = one; fin ]"), vec![]);
+        assert_eq!(errors("[ a = c;\n b = 1; ]"), vec![
+            ("cannot find `c` in this scope".into(), 1),
+        ]);
+    }
 
+    #[test]
+    fn recovered() {
+        let src = SrcFile::new("main.na", "{\n  a = ( ,, );\n  a\n}");
+        let (nana, diags) = parse(&src);
+        assert_eq!(diags.len(), 1);
+        let diags = resolve(nana.unwrap().flatten()).unwrap_err();
+        let errors: Vec<_> = diags.iter()
+            .map(|d| (d.msg.as_str(), d.span().unwrap().start.line))
+            .collect();
+        assert_eq!(errors, vec![("erroneous expression", 2)]);
+    }
+
+    #[test]
+    fn builtins() {
+        let src = SrcFile::new("main.na", "(+) = 0; (+)");
+        let (nana, _) = parse(&src);
+        let nana = resolve(nana.unwrap().flatten()).unwrap();
+        let names: Vec<_> = nana.builtins.iter()
+            .map(|def| nana.defs[def.0].inner_ref().as_str())
+            .collect();
+        assert!(names.contains(&"+") && names.contains(&"<"));
+        // shadowed by a binding, which isn't a builtin
+        let uses = nana.body.inner_ref().block.inner_ref().spaces().1;
+        match uses[0].inner_ref() {
+            ra::Expr::Binder(u) => assert!(!nana.builtins.contains(&u.def)),
+            e => panic!("unexpected expr {:?}", e),
+        }
+    }
+
+    #[test]
+    fn captures() {
+        fn value(g: &ra::GatedBlock) -> &ra::GatedBlock {
+            match g.block.inner_ref().spaces().1[0].inner_ref() {
+                ra::Expr::GatedBlock(g) => g,
+                _ => unreachable!(),
+            }
+        }
+        let names = |g: &ra::GatedBlock| g.captures.iter()
+            .map(|c| c.binder.as_str().to_owned())
+            .collect::<Vec<_>>();
+        let text = "{ a = 1; f x = (b = x; |y| (a, b, y)); f }";
+        let src = SrcFile::new("main.na", text);
+        let nana = resolve(parse(&src).0.unwrap().flatten()).unwrap();
+        let set = value(nana.body.inner_ref());
+        let (bds, _, _) = set.block.inner_ref().spaces();
+        let outer = bds[1].inner_ref().src.inner_ref();
+        assert_eq!(names(set), vec![] as Vec<String>);
+        assert_eq!(names(outer), vec!["a"]);
+        assert_eq!(names(value(outer)), vec!["a", "b"]);
+        assert_eq!(errors("[\n  f n = ? n | 0 -> 1 | _ -> f (n - 1);\n]"), vec![
+            ("`f` is captured before it's bound".into(), 2),
+        ]);
+        assert_eq!(errors("[ x = y;\n  y = 1; ]"), vec![
+            ("`y` is referred to before it's bound".into(), 1),
+        ]);
+    }
+
+    #[test]
+    fn sets() {
+        assert_eq!(errors("{ two = one + 1; one = 1; two }"), vec![]);
+        assert_eq!(errors("{ (a, b) = (1, 2);\n a = b; }"), vec![
+            ("`a` is bound more than once in a set block".into(), 2),
+        ]);
+        assert_eq!(errors("{ <..> = m; m = {}; x }"), vec![]);
+    }
+}
@@ -0,0 +1,317 @@
+//! Checks that projections `blk.x`, exposures `<x> = blk` and names
+//! used under `<..> = blk` only reach exposed bindings `x := ..`, but
+//! never closed ones `x = ..`.
+//!
+//! Only blocks known before evaluation are checked, i.e. those written in
+//! place or bound to a binder, through any projection or aliasing.
+
+use crate::diagnostic::{visible::from_visible_error, Diagnostic};
+use crate::resolve::ast::*;
+use std::collections::{HashMap, HashSet};
+
+#[derive(Clone, Debug)]
+pub enum VisibleError {
+    /// A closed binding reached from outside of its block.
+    Closed {
+        name: String,
+        /// Where it's projected or exposed.
+        span: Span,
+        /// Where it's bound.
+        def: Span,
+    },
+}
+
+pub fn visible(nana: &Nana) -> Result<(), Vec<Diagnostic>> {
+    let mut checker = Checker::default();
+    checker.gated_block(nana.body.inner_ref());
+    let errors = checker.check();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors.into_iter().map(from_visible_error).collect())
+    }
+}
+
+/// Where an exposure pattern gets its binders from.
+#[derive(Clone, Copy)]
+enum Source<'a> {
+    Block(&'a GatedBlock),
+    Expr(&'a Expr),
+}
+
+#[derive(Default)]
+struct Checker<'a> {
+    /// Sources of binders bound by a binding as a whole.
+    srcs: HashMap<DefId, &'a GatedBlock>,
+    projections: Vec<(&'a Expr, &'a Spanned<Binder>)>,
+    exposures: Vec<(&'a [Spanned<ExposurePattern>], Source<'a>)>,
+    /// Sources of the names exposed by `<..>`.
+    opens: HashMap<DefId, Source<'a>>,
+    uses: Vec<(&'a Use, Span)>,
+}
+
+impl<'a> Checker<'a> {
+    fn check(&self) -> Vec<VisibleError> {
+        let mut errors = Vec::new();
+        for &(e, b) in self.projections.iter() {
+            let target = self.expr_target(e, &mut HashSet::new());
+            errors.extend(closed(target, b.inner_ref().as_str(), b.span()));
+        }
+        for &(ex, src) in self.exposures.iter() {
+            let target = self.src_target(src);
+            for ex in ex.iter() {
+                if let ExposurePattern::Binder(def) = ex.inner_ref() {
+                    let name = def.binder.as_str();
+                    errors.extend(closed(target, name, ex.span()));
+                }
+            }
+        }
+        for &(u, span) in self.uses.iter() {
+            if let Some(&src) = self.opens.get(&u.def) {
+                let target = self.src_target(src);
+                errors.extend(closed(target, u.binder.as_str(), span));
+            }
+        }
+        errors
+    }
+
+    fn src_target(&self, src: Source<'a>) -> Option<&'a Block> {
+        match src {
+            Source::Block(g) => self.target(g, &mut HashSet::new()),
+            Source::Expr(e) => self.expr_target(e, &mut HashSet::new()),
+        }
+    }
+
+    /// The block a gated block evaluates to, if known; `seen` guards
+    /// against binders bound to themselves.
+    fn target(
+        &self, g: &'a GatedBlock, seen: &mut HashSet<DefId>
+    ) -> Option<&'a Block> {
+        if !g.traces.is_empty() {
+            return None
+        }
+        match g.block.inner_ref() {
+            Block::Tuple(bds, vls) if bds.is_empty() && vls.len() == 1 => {
+                self.expr_target(vls[0].inner_ref(), seen)
+            }
+            block => Some(block),
+        }
+    }
+
+    fn expr_target(
+        &self, e: &'a Expr, seen: &mut HashSet<DefId>
+    ) -> Option<&'a Block> {
+        match e {
+            Expr::Binder(u) if seen.insert(u.def) => {
+                self.target(self.srcs.get(&u.def)?, seen)
+            }
+            Expr::GatedBlock(g) => self.target(g, seen),
+            Expr::Projection(e, b) => {
+                let block = self.expr_target(e.inner_ref(), seen)?;
+                let (bds, _, _) = block.spaces();
+                let src = bds.iter().rev().find_map(|abs| {
+                    let abs = abs.inner_ref();
+                    let name = b.inner_ref().as_str();
+                    binds(abs.trace.inner_ref(), name).then_some(&abs.src)
+                })?;
+                self.target(src.inner_ref(), seen)
+            }
+            _ => None,
+        }
+    }
+
+    fn gated_block(&mut self, g: &'a GatedBlock) {
+        let (bds, vls, pairs) = g.block.inner_ref().spaces();
+        for abs in bds.iter() {
+            let abs = abs.inner_ref();
+            let src = abs.src.inner_ref();
+            self.trace(&abs.trace, Source::Block(src));
+            self.gated_block(src);
+        }
+        for e in vls.iter() {
+            self.expr(e);
+        }
+        for pair in pairs.iter() {
+            self.expr(&pair.inner_ref().key);
+            self.expr(&pair.inner_ref().val);
+        }
+    }
+
+    /// Walks the pattern of a binding or a branch, bound to `src`.
+    fn trace(&mut self, pat: &'a Spanned<Pattern>, src: Source<'a>) {
+        match pat.inner_ref() {
+            Pattern::Alias(al, p) => {
+                self.trace(al, src);
+                self.trace(p, src);
+            }
+            Pattern::Binder(def) => {
+                if let Source::Block(g) = src {
+                    self.srcs.insert(def.id, g);
+                }
+            }
+            Pattern::Exposure(ex) => {
+                for ex in ex.iter() {
+                    if let ExposurePattern::All(def) = ex.inner_ref() {
+                        self.opens.insert(def.id, src);
+                    }
+                }
+                self.exposures.push((ex, src));
+            }
+            _ => self.pattern(pat.inner_ref()),
+        }
+    }
+
+    /// Walks a pattern with unknown source.
+    fn pattern(&mut self, pat: &'a Pattern) {
+        match pat {
+            Pattern::Alias(al, p) => {
+                self.pattern(al.inner_ref());
+                self.pattern(p.inner_ref());
+            }
+            Pattern::Tuple(ps) | Pattern::List(ps) => {
+                for p in ps.iter() {
+                    self.pattern(p.inner_ref());
+                }
+            }
+            Pattern::Map(ps) => {
+                for p in ps.iter() {
+                    self.expr(&p.inner_ref().key);
+                    self.pattern(p.inner_ref().val.inner_ref());
+                }
+            }
+            _ => (),
+        }
+    }
+
+    fn expr(&mut self, e: &'a Spanned<Expr>) {
+        match e.inner_ref() {
+            Expr::Literal(_) => (),
+            Expr::Binder(u) => self.uses.push((u, e.span())),
+            Expr::GatedBlock(g) => self.gated_block(g),
+            Expr::Application(f, a) => {
+                self.expr(f);
+                self.expr(a);
+            }
+            Expr::Projection(blk, b) => {
+                self.projections.push((blk.inner_ref(), b));
+                self.expr(blk);
+            }
+            Expr::Matching(scrutinee, bs) => {
+                self.expr(scrutinee);
+                for b in bs.iter() {
+                    let b = b.inner_ref();
+                    let src = Source::Expr(scrutinee.inner_ref());
+                    self.trace(&b.pattern, src);
+                    self.expr(&b.body);
+                }
+            }
+        }
+    }
+}
+
+/// Whether the pattern binds the name as a whole.
+fn binds(pat: &Pattern, name: &str) -> bool {
+    match pat {
+        Pattern::Alias(al, p) => {
+            binds(al.inner_ref(), name) || binds(p.inner_ref(), name)
+        }
+        Pattern::Binder(def) => def.binder.as_str() == name,
+        _ => false,
+    }
+}
+
+/// Finds the binding of the name in the block, the last one if shadowed,
+/// and reports it if closed.
+fn closed(
+    block: Option<&Block>, name: &str, span: Span
+) -> Option<VisibleError> {
+    let (bds, _, _) = block?.spaces();
+    let (def, exposed) = bds.iter().rev().find_map(|abs| {
+        let abs = abs.inner_ref();
+        let def = binder_span(&abs.trace, name)?;
+        Some((def, abs.exposed))
+    })?;
+    if exposed {
+        return None
+    }
+    let name = name.to_owned();
+    Some(VisibleError::Closed { name, span, def })
+}
+
+/// The span of the binder of the name in the pattern, if bound.
+fn binder_span(pat: &Spanned<Pattern>, name: &str) -> Option<Span> {
+    match pat.inner_ref() {
+        Pattern::Alias(al, p) => {
+            binder_span(al, name).or_else(|| binder_span(p, name))
+        }
+        Pattern::Binder(def) if def.binder.as_str() == name => {
+            Some(pat.span())
+        }
+        Pattern::Exposure(ex) => ex.iter().find_map(|ex| {
+            match ex.inner_ref() {
+                ExposurePattern::Binder(def) if def.binder.as_str() == name => {
+                    Some(ex.span())
+                }
+                _ => None,
+            }
+        }),
+        Pattern::Tuple(ps) | Pattern::List(ps) => {
+            ps.iter().find_map(|p| binder_span(p, name))
+        }
+        Pattern::Map(ps) => {
+            ps.iter().find_map(|p| binder_span(&p.inner_ref().val, name))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::external::parse;
+    use crate::resolve::resolve;
+    use crate::Flatten;
+
+    /// The names and lines of the closed bindings reached.
+    fn closed(text: &str) -> Vec<(String, usize)> {
+        let src = SrcFile::new("main.na", text);
+        let (nana, diags) = parse(&src);
+        assert!(diags.is_empty());
+        let nana = resolve(nana.unwrap().flatten()).unwrap();
+        let mut checker = Checker::default();
+        checker.gated_block(nana.body.inner_ref());
+        checker.check().into_iter().map(|err| match err {
+            VisibleError::Closed { name, span, .. } => (name, span.start.line),
+        }).collect()
+    }
+
+    #[test]
+    fn projections() {
+        let text = "[
+            blk = [ x = 1; y := 2; x = x; ];
+            blk' = blk;
+            (blk.y, blk'.x)
+        ]";
+        assert_eq!(closed(text), vec![("x".into(), 4)]);
+        let text = "[
+            outer = ( inner := { a = 1; b := 2; }; );
+            outer.inner.a
+        ]";
+        assert_eq!(closed(text), vec![("a".into(), 3)]);
+    }
+
+    #[test]
+    fn exposures() {
+        let text = "{
+            blk = { x := 1; (y, z) = (2, 3); };
+            <x; y> = blk;
+            ? blk | <z> -> z
+        }";
+        assert_eq!(closed(text), vec![("y".into(), 3), ("z".into(), 4)]);
+        let text = "[
+            m = { x := 1; y = 2; };
+            ( <..> = m; (x, y) )
+        ]";
+        assert_eq!(closed(text), vec![("y".into(), 3)]);
+    }
+}
@@ -0,0 +1,62 @@
+//! Golden tests of emitting CSS: each `css/*.na` is emitted and compared
+//! with the `.css` beside it, the `.min.css` if any when compact, or the
+//! `.err` when rejected. `UPDATE_GOLDEN=1` writes the files instead.
+
+use nana_compiler::{
+    base::SrcFile,
+    diagnostic::emit::from_emit_error,
+    emit::{css, Css},
+    eval::eval,
+    external::parse,
+    Flatten,
+};
+use std::path::{Path, PathBuf};
+
+fn emit(src: &SrcFile, config: &Css) -> Result<String, String> {
+    let (nana, diags) = parse(src);
+    assert!(diags.is_empty(), "{} doesn't parse", src.name);
+    let value = eval(nana.unwrap().flatten()).expect("a value");
+    css(&value, config).map_err(|err| from_emit_error(err).render(src))
+}
+
+fn check(golden: &Path, out: &str, failures: &mut Vec<PathBuf>) {
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::write(golden, format!("{}\n", out.trim_end())).unwrap();
+        return
+    }
+    let expected = std::fs::read_to_string(golden).unwrap_or_default();
+    if expected.trim_end() != out.trim_end() {
+        eprintln!("--- {}\n{}", golden.display(), out);
+        failures.push(golden.to_owned());
+    }
+}
+
+#[test]
+fn golden() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/css");
+    let mut cases: Vec<_> = std::fs::read_dir(&dir).unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "na"))
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty());
+    let mut failures = Vec::new();
+    for case in cases {
+        let text = std::fs::read_to_string(&case).unwrap();
+        let name = case.file_name().unwrap().to_string_lossy().into_owned();
+        let src = SrcFile::new(name, text);
+        match emit(&src, &Css::default()) {
+            Ok(out) => {
+                check(&case.with_extension("css"), &out, &mut failures);
+                let min = case.with_extension("min.css");
+                if min.exists() {
+                    let config = Css { pretty: false };
+                    let out = emit(&src, &config).unwrap();
+                    check(&min, &out, &mut failures);
+                }
+            }
+            Err(err) => check(&case.with_extension("err"), &err, &mut failures),
+        }
+    }
+    assert!(failures.is_empty(), "mismatched: {:?}", failures);
+}